@@ -10,7 +10,7 @@ use model_registry::ModelRegistry;
 struct ClientState {
     camera_state: components::Placement,
     camera_target: components::Placement,
-    camera_tracked_entity_id: EntityId,
+    camera_tracked_entity_id: Option<EntityId>,
     cursor_position: components::Placement,
     vectoracious: vectoracious::Context,
 }
@@ -18,16 +18,19 @@ struct ClientState {
 impl ClientState {
     fn tick(&mut self, world: &mut GameWorld) {
         // Update the camera target based on the tracked entity
+        let Some(tracked_entity_id) = self.camera_tracked_entity_id else {
+            return;
+        };
         world.with_ecs_world(|world| {
             if let Some((placement, physics)) = ecs_get!(
                 world,
-                self.camera_tracked_entity_id,
+                tracked_entity_id,
                 cur components::Placement,
                 cur Option<components::Physics>
             ) {
                 self.camera_target.position = placement.position
                     + physics
-                        .map(|physics| physics.velocity * 50.0)
+                        .map(|physics| physics.velocity * (50.0 / 60.0))
                         .unwrap_or(Default::default());
             }
         });
@@ -107,7 +110,25 @@ fn main() {
     let vectoracious = vectoracious::Context::initialize(&video, windowbuilder)
         .expect("Couldn't initialize vectoracious. Bummer!");
     let mut should_quit = false;
-    let mut world = GameWorld::new_test_world();
+    let level_source = std::fs::read_to_string("mechalicious-client/data/test.level")
+        .expect("could not read level file");
+    let mut world = GameWorld::load_level(&level_source)
+        .unwrap_or_else(|err| panic!("Unable to parse test.level: {err}"));
+    // one player for every team that starts out owning a spawn point. we're
+    // the first one, the rest just sit there for now
+    let mut team_ids: Vec<components::TeamId> = ecs_iter!(
+        world.get_ecs_world(),
+        cur components::SpawnPoint,
+        cur components::Team
+    )
+    .filter_map(|(_entity_id, _spawn_point, team)| team.0)
+    .collect();
+    team_ids.sort();
+    team_ids.dedup();
+    let player_ids: Vec<components::PlayerId> = team_ids
+        .into_iter()
+        .map(|team_id| world.add_player(team_id))
+        .collect();
     let mut metronome = Metronome::new(
         RealtimeNowSource::new(),
         ftvf::Rate::per_second(60, 1), // want 60 ticks per 1 second
@@ -115,7 +136,9 @@ fn main() {
     );
     let mut model_registry =
         ModelRegistry::new(PathBuf::from("mechalicious-client/data".to_string()));
-    let player_id = 3;
+    let player_id = *player_ids
+        .first()
+        .expect("level has no spawn points that belong to a team");
     let mut client_state = ClientState {
         camera_state: components::Placement {
             scale: 16.0,
@@ -125,7 +148,7 @@ fn main() {
             scale: 3.0,
             ..Default::default()
         },
-        camera_tracked_entity_id: player_id,
+        camera_tracked_entity_id: None,
         cursor_position: components::Placement {
             scale: 0.1,
             ..Default::default()
@@ -162,6 +185,7 @@ fn main() {
                         Keycode::S => going_down = true,
                         Keycode::A => going_left = true,
                         Keycode::D => going_right = true,
+                        Keycode::E => controls.interact = true,
                         Keycode::F4 if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
                             should_quit = true;
                             break;
@@ -179,9 +203,18 @@ fn main() {
                         Keycode::S => going_down = false,
                         Keycode::A => going_left = false,
                         Keycode::D => going_right = false,
+                        Keycode::E => controls.interact = false,
                         _ => (),
                     }
                 }
+                Event::MouseButtonDown {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    ..
+                } => controls.fire = true,
+                Event::MouseButtonUp {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    ..
+                } => controls.fire = false,
                 Event::MouseMotion { x, y, .. } => {
                     // get the camera transform, use it to transform x and y
                     let x = (x as f32) / (width as f32 * 0.5) - 1.0;
@@ -222,7 +255,15 @@ fn main() {
         ))) {
             match reading {
                 Reading::Tick => {
-                    world.tick(&[(player_id, &controls)]);
+                    // nothing to steer while we're waiting to respawn
+                    match world.get_player_mech(player_id) {
+                        Some(mech_id) => world.tick(&[(mech_id, &controls)]),
+                        None => world.tick(&[]),
+                    }
+                    // the camera stays where it was until we respawn
+                    if let Some(mech_id) = world.get_player_mech(player_id) {
+                        client_state.camera_tracked_entity_id = Some(mech_id);
+                    }
                     // do camera???
                     client_state.tick(&mut world);
                 }
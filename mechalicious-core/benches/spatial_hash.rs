@@ -0,0 +1,148 @@
+// Run with `cargo bench -p mechalicious-core`. Compares the spatial hash
+// against just looking at everything, at a few different world sizes.
+#![feature(test)]
+extern crate test;
+
+use mechalicious_core::{spatial::SpatialHash, Point};
+use nalgebra::point;
+use rand::prelude::*;
+use test::{black_box, Bencher};
+
+// how many "what's near here?" questions each iteration asks
+const QUERY_COUNT: usize = 1000;
+const QUERY_RADIUS: f32 = 1.5;
+
+// Spreads `count` things out so the density stays about the same as the test
+// level, no matter how many there are.
+fn make_entities(count: usize) -> Vec<(u64, Point, f32)> {
+    let mut rng = StdRng::seed_from_u64(count as u64);
+    let half_width = (count as f32).sqrt() * 0.4;
+    (0..count)
+        .map(|index| {
+            let x = rng.gen_range(-half_width..half_width);
+            let y = rng.gen_range(-half_width..half_width);
+            (index as u64, point![x, y], rng.gen_range(0.05..0.5))
+        })
+        .collect()
+}
+
+fn make_queries(entities: &[(u64, Point, f32)]) -> Vec<Point> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..QUERY_COUNT)
+        .map(|_| entities.choose(&mut rng).unwrap().1)
+        .collect()
+}
+
+fn build_hash(entities: &[(u64, Point, f32)]) -> SpatialHash {
+    let mut spatial_hash = SpatialHash::default();
+    for &(entity_id, position, radius) in entities.iter() {
+        spatial_hash.insert(entity_id, position, radius);
+    }
+    spatial_hash
+}
+
+fn bench_hash(b: &mut Bencher, count: usize) {
+    let entities = make_entities(count);
+    let queries = make_queries(&entities);
+    let mut spatial_hash = build_hash(&entities);
+    b.iter(|| {
+        // we rebuild every tick, so that's part of the cost
+        spatial_hash.clear();
+        for &(entity_id, position, radius) in entities.iter() {
+            spatial_hash.insert(entity_id, position, radius);
+        }
+        let mut found = 0;
+        for query in queries.iter() {
+            found += spatial_hash.query_radius(*query, QUERY_RADIUS).len();
+        }
+        black_box(found)
+    });
+}
+
+fn bench_brute_force(b: &mut Bencher, count: usize) {
+    let entities = make_entities(count);
+    let queries = make_queries(&entities);
+    b.iter(|| {
+        let mut found = 0;
+        for query in queries.iter() {
+            found += entities
+                .iter()
+                .filter(|(_, position, radius)| {
+                    let reach = QUERY_RADIUS + radius;
+                    (position - query).magnitude_squared() <= reach * reach
+                })
+                .count();
+        }
+        black_box(found)
+    });
+}
+
+#[bench]
+fn query_radius_hash_1k(b: &mut Bencher) {
+    bench_hash(b, 1_000);
+}
+
+#[bench]
+fn query_radius_hash_10k(b: &mut Bencher) {
+    bench_hash(b, 10_000);
+}
+
+#[bench]
+fn query_radius_hash_50k(b: &mut Bencher) {
+    bench_hash(b, 50_000);
+}
+
+#[bench]
+fn query_radius_brute_force_1k(b: &mut Bencher) {
+    bench_brute_force(b, 1_000);
+}
+
+#[bench]
+fn query_radius_brute_force_10k(b: &mut Bencher) {
+    bench_brute_force(b, 10_000);
+}
+
+#[bench]
+fn query_radius_brute_force_50k(b: &mut Bencher) {
+    bench_brute_force(b, 50_000);
+}
+
+#[bench]
+fn nearest_hash_10k(b: &mut Bencher) {
+    let entities = make_entities(10_000);
+    let queries = make_queries(&entities);
+    let spatial_hash = build_hash(&entities);
+    b.iter(|| {
+        let mut total = 0;
+        for query in queries.iter() {
+            // skip whatever we're standing on, like a targeting system would
+            total += spatial_hash
+                .nearest(*query, f32::INFINITY, |entry| entry.position != *query)
+                .map(|entry| entry.entity_id)
+                .unwrap_or(0);
+        }
+        black_box(total)
+    });
+}
+
+#[bench]
+fn nearest_brute_force_10k(b: &mut Bencher) {
+    let entities = make_entities(10_000);
+    let queries = make_queries(&entities);
+    b.iter(|| {
+        let mut total = 0;
+        for query in queries.iter() {
+            total += entities
+                .iter()
+                .filter(|(_, position, _)| position != query)
+                .min_by(|a, b| {
+                    (a.1 - query)
+                        .magnitude_squared()
+                        .total_cmp(&(b.1 - query).magnitude_squared())
+                })
+                .map(|(entity_id, _, _)| *entity_id)
+                .unwrap_or(0);
+        }
+        black_box(total)
+    });
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::*;
+
+/// Why resources moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TransactionReason {
+    /// What a team gets when its first player shows up.
+    StartingResources,
+    NodeIncome,
+    CoreDrain,
+    Respawn,
+    Purchase,
+    WaveBonus,
+    /// For shooting a goat.
+    Bounty,
+}
+
+impl TransactionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionReason::StartingResources => "starting_resources",
+            TransactionReason::NodeIncome => "node_income",
+            TransactionReason::CoreDrain => "core_drain",
+            TransactionReason::Respawn => "respawn",
+            TransactionReason::Purchase => "purchase",
+            TransactionReason::WaveBonus => "wave_bonus",
+            TransactionReason::Bounty => "bounty",
+        }
+    }
+}
+
+/// One line in the bank's books.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transaction {
+    pub tick: u64,
+    pub team_id: TeamId,
+    pub reason: TransactionReason,
+    /// Positive for credits, negative for debits.
+    pub amount: f32,
+    /// What the team had right after this.
+    pub balance: f32,
+}
+
+/// How many resources each team has, and how they got them. Every change to
+/// a balance goes through `credit` or `debit`. The bank only hangs on to the
+/// transactions that haven't made it into the `GameWorld`'s books yet (see
+/// `TransactionLog`), so it stays small however long the match goes on.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceBank {
+    balances: BTreeMap<TeamId, f32>,
+    // everything that's ever gone in or out, for the match stats
+    earned: BTreeMap<TeamId, f32>,
+    spent: BTreeMap<TeamId, f32>,
+    pending: Vec<Transaction>,
+    // which tick transactions are happening during right now
+    tick: u64,
+}
+
+impl ResourceBank {
+    pub fn has_account(&self, team_id: TeamId) -> bool {
+        self.balances.contains_key(&team_id)
+    }
+    pub fn balance(&self, team_id: TeamId) -> f32 {
+        self.balances.get(&team_id).copied().unwrap_or(0.0)
+    }
+    /// Everything the team has ever been credited.
+    pub fn earned(&self, team_id: TeamId) -> f32 {
+        self.earned.get(&team_id).copied().unwrap_or(0.0)
+    }
+    /// Everything the team has ever been debited.
+    pub fn spent(&self, team_id: TeamId) -> f32 {
+        self.spent.get(&team_id).copied().unwrap_or(0.0)
+    }
+    /// Every team that has ever had an account.
+    pub fn teams(&self) -> impl Iterator<Item = TeamId> + '_ {
+        self.balances.keys().copied()
+    }
+    pub fn credit(&mut self, team_id: TeamId, amount: f32, reason: TransactionReason) {
+        let balance = self.balances.entry(team_id).or_default();
+        *balance += amount;
+        let balance = *balance;
+        *self.earned.entry(team_id).or_default() += amount;
+        self.record(team_id, reason, amount, balance);
+    }
+    /// Takes `amount` out of the team's account, if they can afford it.
+    /// Returns whether they could. Nothing gets logged if they couldn't.
+    pub fn debit(&mut self, team_id: TeamId, amount: f32, reason: TransactionReason) -> bool {
+        let balance = self.balances.entry(team_id).or_default();
+        if *balance < amount {
+            return false;
+        }
+        *balance -= amount;
+        let balance = *balance;
+        *self.spent.entry(team_id).or_default() += amount;
+        self.record(team_id, reason, -amount, balance);
+        true
+    }
+    fn record(&mut self, team_id: TeamId, reason: TransactionReason, amount: f32, balance: f32) {
+        merge_transaction(
+            &mut self.pending,
+            Transaction {
+                tick: self.tick,
+                team_id,
+                reason,
+                amount,
+                balance,
+            },
+        );
+    }
+    pub(crate) fn advance_tick(&mut self) {
+        self.tick += 1;
+    }
+    /// The tick that new transactions get stamped with: the one that's
+    /// running, or if we're between ticks, the last one that ran. Ticks are
+    /// numbered from 1; anything from before the first tick is 0.
+    pub fn current_tick(&self) -> u64 {
+        self.tick
+    }
+    /// The transactions that haven't been handed over to the books yet.
+    pub fn pending(&self) -> &[Transaction] {
+        &self.pending
+    }
+    pub(crate) fn take_pending(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+// Income trickles in every tick, from every node. One line per team per
+// reason per tick is plenty. `transactions` has to be in tick order.
+fn merge_transaction(transactions: &mut Vec<Transaction>, transaction: Transaction) {
+    let same_tick = transactions
+        .iter_mut()
+        .rev()
+        .take_while(|x| x.tick == transaction.tick);
+    for existing in same_tick {
+        if existing.team_id == transaction.team_id && existing.reason == transaction.reason {
+            existing.amount += transaction.amount;
+            existing.balance = transaction.balance;
+            return;
+        }
+    }
+    transactions.push(transaction);
+}
+
+/// The bank's books: every transaction so far, oldest first. This lives on
+/// the `GameWorld`, next to the events, rather than in the world itself, so
+/// it doesn't get copied along with the world every tick.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionLog {
+    transactions: Vec<Transaction>,
+}
+
+impl TransactionLog {
+    pub(crate) fn extend(&mut self, transactions: impl IntoIterator<Item = Transaction>) {
+        for transaction in transactions {
+            merge_transaction(&mut self.transactions, transaction);
+        }
+    }
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+    /// The transactions that happened during `tick`.
+    pub fn transactions_for_tick(&self, tick: u64) -> &[Transaction] {
+        let start = self.transactions.partition_point(|x| x.tick < tick);
+        let end = self.transactions.partition_point(|x| x.tick <= tick);
+        &self.transactions[start..end]
+    }
+    /// The whole log, as CSV.
+    pub fn export_csv(&self) -> String {
+        let mut ret = "tick,team,reason,amount,balance\n".to_string();
+        for transaction in self.transactions.iter() {
+            writeln!(
+                ret,
+                "{},{},{},{},{}",
+                transaction.tick,
+                transaction.team_id.0,
+                transaction.reason.as_str(),
+                transaction.amount,
+                transaction.balance
+            )
+            .unwrap();
+        }
+        ret
+    }
+}
+
+impl GameWorld {
+    pub fn get_balance(&self, team_id: TeamId) -> f32 {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_singleton!(world, cur ResourceBank).balance(team_id)
+    }
+    /// Everything that went in or out of anybody's bank during `tick`. See
+    /// `ResourceBank::current_tick`.
+    pub fn get_transactions(&self, tick: u64) -> Vec<Transaction> {
+        let world: &EcsWorld = &self.ecs_world;
+        let pending = ecs_singleton!(world, cur ResourceBank).pending();
+        let mut ret = TransactionLog::default();
+        ret.extend(
+            self.transactions
+                .transactions_for_tick(tick)
+                .iter()
+                .cloned(),
+        );
+        ret.extend(pending.iter().filter(|x| x.tick == tick).cloned());
+        ret.transactions
+    }
+    pub fn export_transactions_csv(&self) -> String {
+        let world: &EcsWorld = &self.ecs_world;
+        let pending = ecs_singleton!(world, cur ResourceBank).pending();
+        let mut log = self.transactions.clone();
+        log.extend(pending.iter().cloned());
+        log.export_csv()
+    }
+}
@@ -0,0 +1,551 @@
+use std::collections::BTreeMap;
+
+use super::*;
+
+// How much threat everybody loses per second, so that the boss eventually
+// forgets about whoever poked it a minute ago.
+const THREAT_DECAY: f32 = 10.0;
+// How much threat being right up in the boss's face is worth. It falls off to
+// nothing at the edge of its aggro radius.
+const PROXIMITY_THREAT: f32 = 50.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttackPattern {
+    /// Point straight at the target and shoot.
+    Aimed,
+    /// Wave the gun back and forth across the target, `arc` radians wide.
+    Sweep { arc: f32, period: f32 },
+    /// Spin around, spraying everywhere.
+    Spin { turns_per_second: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BossMovement {
+    /// Stay home.
+    Hold,
+    /// Go after the target, but never more than `leash` away from home.
+    Chase { leash: f32 },
+    /// Circle around home.
+    Orbit { radius: f32, period: f32 },
+}
+
+/// A boss can't be hurt for `closed` seconds, then can for `open` seconds,
+/// over and over.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VulnerableWindow {
+    pub closed: f32,
+    pub open: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct BossPhase {
+    /// This phase starts once the boss is down to this much of its health
+    /// (0.0 to 1.0).
+    pub health_fraction: f32,
+    pub attack: AttackPattern,
+    pub movement: BossMovement,
+    pub fire_rate: f32, // shots per second
+    pub damage: f32,    // per shot
+    /// `None` means it can be hurt the whole time.
+    pub vulnerable_window: Option<VulnerableWindow>,
+}
+
+/// A big thing that fights on its own. It goes through its `phases` in order
+/// as it gets hurt, and never goes back.
+#[derive(Clone, Debug)]
+pub struct Boss {
+    /// Highest `health_fraction` first. The first one is where it starts.
+    pub phases: Vec<BossPhase>,
+    pub phase: usize,
+    /// How long we've been in this phase, in seconds.
+    pub phase_time: f32,
+    pub home: Point,
+    /// It doesn't notice anything further away than this.
+    pub aggro_radius: f32,
+    pub target: Option<EntityId>,
+    /// How much each attacker has hurt it lately. Hits that bounced off
+    /// while it was invulnerable don't count.
+    pub threat: BTreeMap<EntityId, f32>,
+    /// The `CaptureZone` that can't be captured while this is alive.
+    pub guarding: Option<EntityId>,
+}
+
+impl Boss {
+    pub fn new(
+        home: Point,
+        aggro_radius: f32,
+        phases: Vec<BossPhase>,
+        guarding: Option<EntityId>,
+    ) -> Boss {
+        assert!(!phases.is_empty());
+        Boss {
+            phases,
+            phase: 0,
+            phase_time: 0.0,
+            home,
+            aggro_radius,
+            target: None,
+            threat: BTreeMap::new(),
+            guarding,
+        }
+    }
+    pub fn current_phase(&self) -> &BossPhase {
+        &self.phases[self.phase]
+    }
+    /// Whether it can be hurt right now.
+    pub fn is_vulnerable(&self) -> bool {
+        match &self.current_phase().vulnerable_window {
+            None => true,
+            Some(window) => {
+                let cycle = window.closed + window.open;
+                cycle <= 0.0 || self.phase_time % cycle >= window.closed
+            }
+        }
+    }
+}
+
+/// The phases of the boss that guards the core. It starts out planted on
+/// the core picking people off, then starts circling and sweeping, and ends
+/// up chasing people around and spraying everywhere. It only lets its guard
+/// down now and then once it's been hurt.
+pub fn guardian_phases() -> Vec<BossPhase> {
+    vec![
+        BossPhase {
+            health_fraction: 1.0,
+            attack: AttackPattern::Aimed,
+            movement: BossMovement::Hold,
+            fire_rate: 4.0,
+            damage: 8.0,
+            vulnerable_window: None,
+        },
+        BossPhase {
+            health_fraction: 0.6,
+            attack: AttackPattern::Sweep {
+                arc: 1.2,
+                period: 2.0,
+            },
+            movement: BossMovement::Orbit {
+                radius: 3.0,
+                period: 8.0,
+            },
+            fire_rate: 8.0,
+            damage: 6.0,
+            vulnerable_window: Some(VulnerableWindow {
+                closed: 3.0,
+                open: 2.0,
+            }),
+        },
+        BossPhase {
+            health_fraction: 0.25,
+            attack: AttackPattern::Spin {
+                turns_per_second: 0.5,
+            },
+            movement: BossMovement::Chase { leash: 8.0 },
+            fire_rate: 12.0,
+            damage: 5.0,
+            vulnerable_window: Some(VulnerableWindow {
+                closed: 2.0,
+                open: 3.0,
+            }),
+        },
+    ]
+}
+
+/// The phases of a miniboss. It sits on its node and shoots, until it's
+/// nearly dead, then it comes out swinging.
+pub fn miniboss_phases() -> Vec<BossPhase> {
+    vec![
+        BossPhase {
+            health_fraction: 1.0,
+            attack: AttackPattern::Aimed,
+            movement: BossMovement::Hold,
+            fire_rate: 3.0,
+            damage: 6.0,
+            vulnerable_window: None,
+        },
+        BossPhase {
+            health_fraction: 0.4,
+            attack: AttackPattern::Sweep {
+                arc: 0.8,
+                period: 1.5,
+            },
+            movement: BossMovement::Chase { leash: 4.0 },
+            fire_rate: 6.0,
+            damage: 5.0,
+            vulnerable_window: None,
+        },
+    ]
+}
+
+// Bosses are all the same kind of thing, just bigger or smaller.
+struct BossStats {
+    scale: f32,
+    mass: f32,
+    max_health: f32,
+    aggro_radius: f32,
+    phases: Vec<BossPhase>,
+}
+
+// Spawns a boss, and if it's `guarding` something, tells that about it.
+fn spawn_boss(
+    world: &mut EcsWorld,
+    home: Point,
+    team: Team,
+    guarding: Option<EntityId>,
+    stats: BossStats,
+) -> EntityId {
+    let first_phase = stats.phases[0].clone();
+    let boss = ecs_spawn!(
+        world,
+        Placement {
+            position: home,
+            angle: 0.0,
+            scale: stats.scale,
+        },
+        Physics {
+            mass: stats.mass,
+            moment: stats.mass,
+            force: vector![0.0, 0.0],
+            torque: 0.0,
+            velocity: vector![0.0, 0.0],
+            angular_velocity: 0.0,
+        },
+        Collider {
+            radius: 1.0,
+            restitution: 0.1,
+            friction: 0.5,
+        },
+        ShipControls::default(),
+        ShipControlCharacteristics {
+            thrust: stats.mass * 12.0,
+            turn_torque: stats.mass * 60.0,
+            ..Default::default()
+        },
+        Weapon {
+            fire_rate: first_phase.fire_rate,
+            muzzle_offset: vector![1.2, 0.0],
+            projectile_speed: 14.0,
+            spread: 0.1,
+            projectile_lifetime: 1.5,
+            damage: first_phase.damage,
+            cooldown: 0.0,
+        },
+        Health::new(stats.max_health, DeathBehavior::Wreck),
+        team,
+        Boss::new(home, stats.aggro_radius, stats.phases, guarding),
+        Visible {
+            model_path: "guardian.v2d",
+        },
+    );
+    if let Some(guarding) = guarding {
+        if let Some(mut zone) = ecs_get!(world, guarding, mut CaptureZone) {
+            zone.guardian = Some(boss);
+        }
+    }
+    boss
+}
+
+/// Spawns the big boss that guards `core`.
+pub(crate) fn spawn_guardian(world: &mut EcsWorld, home: Point, core: EntityId) -> EntityId {
+    spawn_boss(
+        world,
+        home,
+        Team::NEUTRAL,
+        Some(core),
+        BossStats {
+            scale: 1.5,
+            mass: 20.0,
+            max_health: 2000.0,
+            aggro_radius: 10.0,
+            phases: guardian_phases(),
+        },
+    )
+}
+
+/// Spawns a miniboss that guards `node`.
+pub(crate) fn spawn_miniboss(
+    world: &mut EcsWorld,
+    home: Point,
+    team: Team,
+    node: EntityId,
+) -> EntityId {
+    spawn_boss(
+        world,
+        home,
+        team,
+        Some(node),
+        BossStats {
+            scale: 0.8,
+            mass: 6.0,
+            max_health: 600.0,
+            aggro_radius: 7.0,
+            phases: miniboss_phases(),
+        },
+    )
+}
+
+/// Which way to push to get to `destination` and stop there, as a
+/// `ShipControls::movement`.
+pub(crate) fn steer_toward(position: Point, velocity: Vector, destination: Point) -> Vector {
+    let push = (destination - position) - velocity * 0.5;
+    if push.magnitude_squared() > 1.0 {
+        push.normalize()
+    } else {
+        push
+    }
+}
+
+/// Thinks for every boss: who hurt it, which phase it should be in, who it's
+/// after, and where it's going to move and shoot. What it decides goes in its
+/// `ShipControls`, so it moves and shoots next tick like anybody else.
+pub(crate) fn update_bosses(
+    world: &EcsWorld,
+    level_geometry: &LevelGeometry,
+    spatial_hash: &SpatialHash,
+    dt: f32,
+    damage: &[Damage],
+    events: &mut Vec<GameEvent>,
+) {
+    for hit in damage.iter() {
+        let Some(attacker) = hit.attacker else {
+            continue;
+        };
+        if let Some(mut boss) = ecs_get!(world, hit.target, mut Boss) {
+            *boss.threat.entry(attacker).or_default() += hit.amount;
+        }
+    }
+    for (entity_id, boss, placement, physics, health, controls, weapon, team) in ecs_iter!(
+        world,
+        mut Boss,
+        cur Placement,
+        cur Physics,
+        mut Health,
+        mut ShipControls,
+        mut Weapon,
+        cur Team
+    ) {
+        // Phase transitions. Only forward, even if something heals it.
+        let next_phase = boss
+            .phases
+            .iter()
+            .rposition(|phase| health.fraction() <= phase.health_fraction)
+            .unwrap_or(0);
+        if next_phase > boss.phase {
+            boss.phase = next_phase;
+            boss.phase_time = 0.0;
+            events.push(GameEvent::BossPhaseChanged {
+                entity_id,
+                phase: next_phase,
+            });
+        } else {
+            boss.phase_time += dt;
+        }
+        let phase = boss.current_phase().clone();
+        health.invulnerable = !boss.is_vulnerable();
+        weapon.fire_rate = phase.fire_rate;
+        weapon.damage = phase.damage;
+        // Forget about people who went away, or haven't hurt us in a while.
+        boss.threat.retain(|_, threat| {
+            *threat -= THREAT_DECAY * dt;
+            *threat > 0.0
+        });
+        // Pick a target. Whoever's hurt us the most, or is closest.
+        let position = placement.position;
+        let aggro_radius = boss.aggro_radius;
+        let mut best: Option<(f32, EntityId, Point)> = None;
+        for entry in spatial_hash.query_radius(position, aggro_radius) {
+            if entry.entity_id == entity_id {
+                continue;
+            }
+            let Some((other_team, other_health)) =
+                ecs_get!(world, entry.entity_id, cur Team, cur Health)
+            else {
+                continue;
+            };
+            if other_team.0.is_none()
+                || other_team.is_friendly_to(team)
+                || other_health.is_dead()
+                || !level_geometry.line_of_sight(position, entry.position)
+            {
+                continue;
+            }
+            let distance = (entry.position - position).magnitude();
+            let score = boss.threat.get(&entry.entity_id).copied().unwrap_or(0.0)
+                + PROXIMITY_THREAT * (1.0 - distance / aggro_radius).max(0.0);
+            // ties go to the lower entity ID
+            let better = match best {
+                None => true,
+                Some((best_score, best_id, _)) => {
+                    score > best_score || (score == best_score && entry.entity_id < best_id)
+                }
+            };
+            if better {
+                best = Some((score, entry.entity_id, entry.position));
+            }
+        }
+        boss.target = best.map(|(_, target, _)| target);
+        let target_position = best.map(|(_, _, target_position)| target_position);
+        // Where to go.
+        let destination = match (&phase.movement, target_position) {
+            (BossMovement::Chase { leash }, Some(target_position)) => {
+                boss.home + (target_position - boss.home).cap_magnitude(*leash)
+            }
+            (BossMovement::Orbit { radius, period }, _) => {
+                let angle = boss.phase_time * TAU / period;
+                boss.home + vector![angle.cos(), angle.sin()] * *radius
+            }
+            _ => boss.home,
+        };
+        controls.movement = steer_toward(position, physics.velocity, destination);
+        // Where to shoot.
+        let aim_angle = match target_position {
+            Some(target_position) => {
+                let delta = target_position - position;
+                delta.y.atan2(delta.x)
+            }
+            None => placement.angle,
+        };
+        let aim_angle = match phase.attack {
+            AttackPattern::Aimed => aim_angle,
+            AttackPattern::Sweep { arc, period } => {
+                aim_angle + arc * 0.5 * (boss.phase_time * TAU / period).sin()
+            }
+            AttackPattern::Spin { turns_per_second } => boss.phase_time * TAU * turns_per_second,
+        };
+        controls.aim = vector![aim_angle.cos(), aim_angle.sin()];
+        controls.fire = boss.target.is_some();
+    }
+}
+
+impl GameWorld {
+    /// The state of the boss `entity_id`, if that's a boss.
+    pub fn get_boss(&self, entity_id: EntityId) -> Option<Boss> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_get!(world, entity_id, cur Boss).cloned()
+    }
+    /// Whatever's keeping the core from being captured, if anything.
+    pub fn get_core_guardian(&self) -> Option<EntityId> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_iter!(world, cur Core, cur CaptureZone)
+            .map(|(_entity_id, _core, zone)| zone.guardian)
+            .next()
+            .flatten()
+            .filter(|guardian| is_alive(world, *guardian))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Points `shooter` at `target` and pulls the trigger.
+    fn shoot_at(world: &GameWorld, shooter: EntityId, target: EntityId) -> ShipControls {
+        let world = world.get_ecs_world();
+        let from = ecs_get!(world, shooter, cur Placement).unwrap().position;
+        let to = ecs_get!(world, target, cur Placement).unwrap().position;
+        ShipControls {
+            aim: to - from,
+            fire: true,
+            ..Default::default()
+        }
+    }
+
+    fn health(world: &GameWorld, entity_id: EntityId) -> Health {
+        let world = world.get_ecs_world();
+        ecs_get!(world, entity_id, cur Health).unwrap().clone()
+    }
+
+    fn threat(world: &GameWorld, boss: EntityId, attacker: EntityId) -> f32 {
+        let boss = world.get_boss(boss).unwrap();
+        boss.threat.get(&attacker).copied().unwrap_or(0.0)
+    }
+
+    #[test]
+    fn guardian_goes_through_its_phases() {
+        let mut world = GameWorld::load_level("spawn 0 0 0\ncore 0 0").unwrap();
+        let guardian = world.get_core_guardian().unwrap();
+        let player = world.add_player(TeamId(0));
+        world.tick(&[]);
+        // somebody to shoot it, who won't die on us
+        let mech = world.get_player_mech(player).unwrap();
+        world.with_ecs_world(|world| {
+            ecs_get!(world, mech, mut Placement).unwrap().position = point![6.0, 0.0];
+            ecs_get!(world, mech, mut Health).unwrap().invulnerable = true;
+        });
+        // It starts out open, so shots land, and it notices who's shooting.
+        for _ in 0..60 {
+            let controls = shoot_at(&world, mech, guardian);
+            world.tick(&[(mech, &controls)]);
+        }
+        assert!(health(&world, guardian).current < 2000.0);
+        assert!(threat(&world, guardian, mech) > 0.0);
+        assert_eq!(world.get_boss(guardian).unwrap().target, Some(mech));
+        // Down to 55%: it starts sweeping, and closes up right away.
+        world.with_ecs_world(|world| {
+            ecs_get!(world, guardian, mut Health).unwrap().current = 1100.0;
+            ecs_get!(world, guardian, mut Boss).unwrap().threat.clear();
+        });
+        let controls = shoot_at(&world, mech, guardian);
+        world.tick(&[(mech, &controls)]);
+        assert!(world.get_events().iter().any(|event| matches!(
+            event,
+            GameEvent::BossPhaseChanged { entity_id, phase: 1 } if *entity_id == guardian
+        )));
+        // Shots bounce off while it's closed, and don't count for anything.
+        for _ in 0..60 {
+            let controls = shoot_at(&world, mech, guardian);
+            world.tick(&[(mech, &controls)]);
+            assert!(health(&world, guardian).invulnerable);
+        }
+        assert_eq!(health(&world, guardian).current, 1100.0);
+        assert_eq!(threat(&world, guardian, mech), 0.0);
+        // It keeps shooting while it's closed up, with its new damage.
+        let world_ref = world.get_ecs_world();
+        let shots: Vec<f32> = ecs_iter!(world_ref, cur Projectile)
+            .filter(|(_entity_id, projectile)| projectile.shooter == guardian)
+            .map(|(_entity_id, projectile)| projectile.damage)
+            .collect();
+        assert!(!shots.is_empty());
+        assert!(shots.iter().all(|damage| *damage == 6.0));
+        // Once it opens up again, shots land again.
+        let mut ticks = 0;
+        while health(&world, guardian).invulnerable {
+            let controls = shoot_at(&world, mech, guardian);
+            world.tick(&[(mech, &controls)]);
+            ticks += 1;
+            assert!(ticks < 600, "the guardian never opened up");
+        }
+        // Nearly there. A couple more hits should do it.
+        world.with_ecs_world(|world| {
+            ecs_get!(world, guardian, mut Health).unwrap().current = 520.0;
+        });
+        let mut entered_last_phase = false;
+        for _ in 0..60 {
+            let controls = shoot_at(&world, mech, guardian);
+            world.tick(&[(mech, &controls)]);
+            entered_last_phase |= world.get_events().iter().any(|event| {
+                matches!(
+                    event,
+                    GameEvent::BossPhaseChanged { entity_id, phase: 2 } if *entity_id == guardian
+                )
+            });
+            if entered_last_phase {
+                break;
+            }
+        }
+        assert!(entered_last_phase);
+        assert!(threat(&world, guardian, mech) > 0.0);
+        assert!(health(&world, guardian).invulnerable);
+    }
+
+    #[test]
+    fn dead_guardians_stop_guarding() {
+        let mut world = GameWorld::load_level("spawn 0 0 0\ncore 0 0").unwrap();
+        let guardian = world.get_core_guardian().unwrap();
+        world.with_ecs_world(|world| {
+            ecs_get!(world, guardian, mut Health).unwrap().current = 0.0;
+        });
+        world.tick(&[]);
+        assert_eq!(world.get_core_guardian(), None);
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeSet;
+
+use super::*;
+
+/// Works out who's standing in each capture zone, moves its progress along,
+/// and hands it over to whoever finished capturing it.
+pub(crate) fn update_capture_zones(
+    world: &EcsWorld,
+    spatial_hash: &SpatialHash,
+    dt: f32,
+    events: &mut Vec<GameEvent>,
+) {
+    let zones: Vec<(EntityId, Point, f32, CapturerKind, Option<EntityId>)> = ecs_iter!(
+        world,
+        cur Placement,
+        cur CaptureZone
+    )
+    .map(|(entity_id, placement, zone)| {
+        (
+            entity_id,
+            placement.position,
+            zone.radius,
+            zone.captured_by,
+            zone.guardian,
+        )
+    })
+    .collect();
+    for (zone_id, position, radius, captured_by, guardian) in zones {
+        let mut present = BTreeSet::new();
+        // While the guardian's around, it's like nobody's here at all.
+        let guarded = guardian
+            .map(|guardian| is_alive(world, guardian))
+            .unwrap_or(false);
+        let nearby = if guarded {
+            vec![]
+        } else {
+            spatial_hash.query_radius(position, radius)
+        };
+        for entry in nearby {
+            let Some((capturer, team, health)) = ecs_get!(
+                world,
+                entry.entity_id,
+                cur Capturer,
+                cur Team,
+                cur Option<Health>
+            ) else {
+                continue;
+            };
+            if capturer.kind != captured_by || health.map(Health::is_dead).unwrap_or(false) {
+                continue;
+            }
+            if let Some(team_id) = team.0 {
+                present.insert(team_id);
+            }
+        }
+        let Some((mut zone, mut owner)) = ecs_get!(world, zone_id, mut CaptureZone, mut Team)
+        else {
+            continue;
+        };
+        let step = dt / zone.capture_time;
+        let mut present = present.into_iter();
+        match (present.next(), present.next()) {
+            // Contested. Nothing moves until somebody wins the fight.
+            (Some(_), Some(_)) => (),
+            // Somebody new is here, and only them.
+            (Some(team_id), None) if owner.0 != Some(team_id) => {
+                if zone.capturing_team != Some(team_id) {
+                    zone.capturing_team = Some(team_id);
+                    zone.progress = 0.0;
+                }
+                zone.progress += step;
+                if zone.progress >= 1.0 {
+                    let old_owner = *owner;
+                    *owner = Team::of(team_id);
+                    zone.capturing_team = None;
+                    zone.progress = 0.0;
+                    events.push(GameEvent::Captured {
+                        entity_id: zone_id,
+                        old_owner,
+                        new_owner: *owner,
+                    });
+                }
+            }
+            // Nobody's here, or only the owners are. Undo any progress.
+            _ => {
+                zone.progress = (zone.progress - step).max(0.0);
+                if zone.progress == 0.0 {
+                    zone.capturing_team = None;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use super::*;
+
+// Everything we need to know about one body while we're bouncing things off
+// each other. Things without `Physics` get zero inverse mass and inverse
+// moment, which makes them immovable.
+struct CollisionBody {
+    entity_id: EntityId,
+    position: Point,
+    radius: f32,
+    inverse_mass: f32,
+    inverse_moment: f32,
+    velocity: Vector,
+    angular_velocity: f32,
+    restitution: f32,
+    friction: f32,
+    touched: bool,
+}
+
+impl CollisionBody {
+    fn is_immovable(&self) -> bool {
+        self.inverse_mass == 0.0
+    }
+    // the velocity of the point on this body that is `offset` from its center
+    fn velocity_at(&self, offset: Vector) -> Vector {
+        self.velocity + vector![-offset.y, offset.x] * self.angular_velocity
+    }
+    fn apply_impulse(&mut self, offset: Vector, impulse: Vector) {
+        self.velocity += impulse * self.inverse_mass;
+        self.angular_velocity += offset.perp(&impulse) * self.inverse_moment;
+    }
+}
+
+fn inverse_or_zero(x: f32) -> f32 {
+    if x > 0.0 {
+        1.0 / x
+    } else {
+        0.0
+    }
+}
+
+/// Separates every pair of overlapping colliders, and exchanges momentum
+/// between them. Anything that gets pushed is kept out of the walls. The
+/// spatial hash has to be up to date before this gets called.
+pub(crate) fn resolve_collisions(
+    world: &EcsWorld,
+    level_geometry: &LevelGeometry,
+    spatial_hash: &SpatialHash,
+) {
+    let mut bodies: Vec<CollisionBody> = ecs_iter!(
+        world,
+        cur Placement,
+        cur Collider,
+        cur Option<Physics>
+    )
+    .map(|(entity_id, placement, collider, physics)| CollisionBody {
+        entity_id,
+        position: placement.position,
+        radius: collider.scaled_radius(placement),
+        inverse_mass: physics.map(|x| inverse_or_zero(x.mass)).unwrap_or(0.0),
+        inverse_moment: physics.map(|x| inverse_or_zero(x.moment)).unwrap_or(0.0),
+        velocity: physics.map(|x| x.velocity).unwrap_or_default(),
+        angular_velocity: physics.map(|x| x.angular_velocity).unwrap_or(0.0),
+        restitution: collider.restitution,
+        friction: collider.friction,
+        touched: false,
+    })
+    .collect();
+    let body_indices: HashMap<EntityId, usize> = bodies
+        .iter()
+        .enumerate()
+        .map(|(index, body)| (body.entity_id, index))
+        .collect();
+    for a_index in 0..bodies.len() {
+        let neighbors = spatial_hash.query_radius(bodies[a_index].position, bodies[a_index].radius);
+        for neighbor in neighbors {
+            // only do each pair once
+            let Some(&b_index) = body_indices.get(&neighbor.entity_id) else {
+                continue;
+            };
+            if b_index <= a_index {
+                continue;
+            }
+            let (head, tail) = bodies.split_at_mut(b_index);
+            collide_pair(&mut head[a_index], &mut tail[0]);
+        }
+    }
+    for body in bodies.into_iter().filter(|x| x.touched) {
+        if let Some((mut placement, mut physics)) =
+            ecs_get!(world, body.entity_id, mut Placement, mut Physics)
+        {
+            let old_position = placement.position;
+            let mut position = body.position;
+            let mut velocity = body.velocity;
+            level_geometry.collide_circle(old_position, &mut position, body.radius, &mut velocity);
+            placement.position = position;
+            physics.velocity = velocity;
+            physics.angular_velocity = body.angular_velocity;
+        }
+    }
+}
+
+fn collide_pair(a: &mut CollisionBody, b: &mut CollisionBody) {
+    if a.is_immovable() && b.is_immovable() {
+        return;
+    }
+    let delta = b.position - a.position;
+    let touching_distance = a.radius + b.radius;
+    let distance_squared = delta.magnitude_squared();
+    if distance_squared >= touching_distance * touching_distance {
+        return;
+    }
+    let distance = distance_squared.sqrt();
+    // the direction from a to b
+    let normal = if distance > 0.0 {
+        delta / distance
+    } else {
+        // exactly on top of each other, just pick a direction
+        vector![1.0, 0.0]
+    };
+    // First, get them out of each other. The lighter one moves more.
+    let total_inverse_mass = a.inverse_mass + b.inverse_mass;
+    let overlap = touching_distance - distance;
+    a.position -= normal * (overlap * a.inverse_mass / total_inverse_mass);
+    b.position += normal * (overlap * b.inverse_mass / total_inverse_mass);
+    a.touched = true;
+    b.touched = true;
+    // Then, if they're still heading into each other, bounce.
+    let a_offset = normal * a.radius;
+    let b_offset = -normal * b.radius;
+    let relative_velocity = b.velocity_at(b_offset) - a.velocity_at(a_offset);
+    let closing_speed = relative_velocity.dot(&normal);
+    if closing_speed >= 0.0 {
+        return;
+    }
+    // The offsets point straight along the normal, so the bounce itself
+    // can't spin anything. Only the friction can.
+    let restitution = a.restitution.max(b.restitution);
+    let bounce = -(1.0 + restitution) * closing_speed / total_inverse_mass;
+    a.apply_impulse(a_offset, -normal * bounce);
+    b.apply_impulse(b_offset, normal * bounce);
+    // Friction pushes against the sliding part of the relative velocity,
+    // using the velocities we have *after* the bounce.
+    let relative_velocity = b.velocity_at(b_offset) - a.velocity_at(a_offset);
+    let sliding = relative_velocity - normal * relative_velocity.dot(&normal);
+    let sliding_speed = sliding.magnitude();
+    if sliding_speed == 0.0 {
+        return;
+    }
+    let tangent = sliding / sliding_speed;
+    let effective_inverse_mass = total_inverse_mass
+        + a_offset.perp(&tangent).powi(2) * a.inverse_moment
+        + b_offset.perp(&tangent).powi(2) * b.inverse_moment;
+    let friction = (a.friction * b.friction).sqrt();
+    let rub = (sliding_speed / effective_inverse_mass).min(friction * bounce);
+    a.apply_impulse(a_offset, tangent * rub);
+    b.apply_impulse(b_offset, -tangent * rub);
+}
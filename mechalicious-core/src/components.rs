@@ -45,24 +45,308 @@ impl Physics {
     }
 }
 
+/// A solid circle. `radius` gets multiplied by `Placement::scale`. Things
+/// without `Physics` can still have one; they just can't be pushed around.
+#[derive(Clone, Debug)]
+pub struct Collider {
+    pub radius: f32,
+    /// 0.0 = thud, 1.0 = boing
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl Collider {
+    pub fn scaled_radius(&self, placement: &Placement) -> f32 {
+        self.radius * placement.scale
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Visible {
     pub model_path: &'static str,
 }
 
+/// Turns a model path that came from a data file into one that can go in a
+/// `Visible`. Each distinct path is only ever leaked once.
+pub fn intern_model_path(model_path: &str) -> &'static str {
+    static INTERNED: std::sync::Mutex<Vec<&'static str>> = std::sync::Mutex::new(Vec::new());
+    let mut interned = INTERNED.lock().unwrap();
+    if let Some(existing) = interned.iter().find(|x| **x == model_path) {
+        return existing;
+    }
+    let leaked: &'static str = Box::leak(model_path.to_string().into_boxed_str());
+    interned.push(leaked);
+    leaked
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct ShipControls {
     pub movement: Vector, // left stick
     pub aim: Vector,      // right stick
     pub fire: bool,
+    pub interact: bool,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TeamId(pub u32);
+
+/// Whose side something is on. Things that are nobody's (a node nobody has
+/// captured yet) are `Team(None)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Team(pub Option<TeamId>);
+
+impl Team {
+    pub const NEUTRAL: Team = Team(None);
+    pub fn of(team_id: TeamId) -> Team {
+        Team(Some(team_id))
+    }
+    /// Both on the same team. Neutral things aren't anybody's friend.
+    pub fn is_friendly_to(&self, other: &Team) -> bool {
+        self.0.is_some() && self.0 == other.0
+    }
+    /// Both on a team, and not the same one. Neutral things aren't anybody's
+    /// enemy either.
+    pub fn is_hostile_to(&self, other: &Team) -> bool {
+        self.0.is_some() && other.0.is_some() && self.0 != other.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FriendlyFire {
+    /// Friendly projectiles hurt.
+    Hit,
+    /// Friendly projectiles fly right through.
+    #[default]
+    PassThrough,
+    /// Friendly projectiles stop, but don't hurt.
+    Blocked,
+}
+
+/// Rules for the whole match.
+#[derive(Clone, Debug)]
+pub struct MatchSettings {
+    /// PvP, PVE, or something else. See `GameMode`.
+    pub mode: std::sync::Arc<dyn GameMode>,
+    /// The match doesn't start until there are this many players.
+    pub min_players: usize,
+    /// Between there being enough players and the match starting.
+    pub countdown: f32, // seconds
+    /// How long the match goes before overtime. `None` for no limit.
+    pub time_limit: Option<f32>, // seconds
+    pub overtime: f32, // seconds
+    /// How long the results hang around before the server moves on.
+    pub post_game: f32, // seconds
+    pub friendly_fire: FriendlyFire,
+    pub respawn_delay: f32, // seconds
+    pub respawn_cost: f32,
+    /// What a team has in the bank when its first player shows up.
+    pub starting_resources: f32,
+    /// How fast a captured core empties into its owner's bank, per second.
+    pub core_drain_rate: f32,
+    /// How fast an owned resource node siphons from the core, per second,
+    /// per tier.
+    pub node_income_rate: f32,
+    pub miniboss_cost: f32,
+    /// A node has to be at least this tier before you can build a miniboss
+    /// on it.
+    pub miniboss_min_tier: u32,
+    /// How many units each team can have at once.
+    pub unit_cap: usize,
+    /// What a team gets for shooting a goat. 0 for nothing.
+    pub goat_bounty: f32,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            mode: default_mode(),
+            min_players: 0,
+            countdown: 0.0,
+            time_limit: None,
+            overtime: 60.0,
+            post_game: 10.0,
+            friendly_fire: FriendlyFire::default(),
+            respawn_delay: 3.0,
+            respawn_cost: 100.0,
+            starting_resources: 300.0,
+            core_drain_rate: 250.0,
+            node_income_rate: 2.0,
+            miniboss_cost: 1000.0,
+            miniboss_min_tier: 3,
+            unit_cap: 20,
+            goat_bounty: 10.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PlayerId(pub u32);
+
+/// Somebody's flying this.
+#[derive(Clone, Debug)]
+pub struct Pilot {
+    pub player: PlayerId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapturerKind {
+    Mech,
+    Unit,
+}
+
+/// Being in a `CaptureZone` counts for this thing's team, if the zone can be
+/// captured by this kind of thing.
+#[derive(Clone, Debug)]
+pub struct Capturer {
+    pub kind: CapturerKind,
+}
+
+/// A place that changes hands when one team's capturers hang around in it
+/// long enough. Who owns it now is its `Team`.
+#[derive(Clone, Debug)]
+pub struct CaptureZone {
+    pub radius: f32,
+    pub capture_time: f32, // seconds, with nobody contesting
+    pub captured_by: CapturerKind,
+    /// Who's taking it, and how far along they are (0.0 to 1.0).
+    pub capturing_team: Option<TeamId>,
+    pub progress: f32,
+    /// Nobody can capture this while this is still alive.
+    pub guardian: Option<EntityId>,
+}
+
+impl CaptureZone {
+    pub fn new(radius: f32, capture_time: f32, captured_by: CapturerKind) -> CaptureZone {
+        CaptureZone {
+            radius,
+            capture_time,
+            captured_by,
+            capturing_team: None,
+            progress: 0.0,
+            guardian: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Weapon {
+    pub fire_rate: f32,           // shots per second
+    pub muzzle_offset: Vector,    // from the center, facing angle 0, scale 1
+    pub projectile_speed: f32,    // on top of the shooter's own velocity
+    pub spread: f32,              // radians, side to side
+    pub projectile_lifetime: f32, // seconds
+    pub damage: f32,              // per projectile
+    pub cooldown: f32,            // seconds until we can shoot again
+}
+
+#[derive(Clone, Debug)]
+pub struct Projectile {
+    pub shooter: EntityId,
+    pub velocity: Vector,
+    pub damage: f32,
+    pub time_left: f32, // seconds
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathBehavior {
+    Despawn,
+    /// Leave a `Wreck` behind, with the same `Placement`, `Visible` and
+    /// `Collider`.
+    Wreck,
+}
+
+#[derive(Clone, Debug)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// Whoever hurt us most recently, so they can get credit for the kill.
+    pub last_attacker: Option<EntityId>,
+    pub on_death: DeathBehavior,
+    /// Damage doesn't do anything while this is set.
+    pub invulnerable: bool,
+}
+
+impl Health {
+    pub fn new(max: f32, on_death: DeathBehavior) -> Health {
+        Health {
+            current: max,
+            max,
+            last_attacker: None,
+            on_death,
+            invulnerable: false,
+        }
+    }
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+    /// How much is left, from 0.0 (dead) to 1.0 (full).
+    pub fn fraction(&self) -> f32 {
+        if self.max > 0.0 {
+            (self.current / self.max).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Wreck {
+    pub was: EntityId,
+}
+
+#[derive(Clone, Debug)]
 pub struct ShipControlCharacteristics {
     pub aim_controller: PidController,
+    pub thrust: f32,      // force at full stick
+    pub turn_torque: f32, // torque at full PID output
+}
+
+impl Default for ShipControlCharacteristics {
+    fn default() -> Self {
+        Self {
+            aim_controller: PidController::default(),
+            thrust: 18.0,
+            turn_torque: 180.0,
+        }
+    }
 }
 
+// All the physics tunables are in per-second units, so that changing `dt`
+// doesn't mean retuning everything.
 #[derive(Clone, Debug)]
 pub struct WorldPhysics {
     pub air_thickness: f32,
+    pub dt: f32, // seconds per tick
+    /// How many pieces to chop each tick into. More = fast things behave
+    /// better, but physics costs more.
+    pub substeps: u32,
+}
+
+impl Default for WorldPhysics {
+    fn default() -> Self {
+        Self {
+            air_thickness: 5.4,
+            dt: 1.0 / 60.0,
+            substeps: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpawnPoint {}
+
+pub const DEFAULT_CORE_RESOURCES: f32 = 5000.0;
+
+/// The big prize in the middle of the map. Whoever owns it (its `Team`) gets
+/// everything that's left in it, fast.
+#[derive(Clone, Debug)]
+pub struct Core {
+    pub resources: f32,
+}
+
+/// A place that, while somebody owns it (its `Team`), slowly siphons
+/// resources out of the core for them. Higher tiers siphon faster.
+#[derive(Clone, Debug)]
+pub struct ResourceNode {
+    pub tier: u32,
 }
@@ -0,0 +1,99 @@
+use super::*;
+
+/// One hit on one thing. Systems that hurt things make these, and the Damage
+/// System applies them all at once.
+#[derive(Clone, Debug)]
+pub struct Damage {
+    pub target: EntityId,
+    pub amount: f32,
+    pub attacker: Option<EntityId>,
+}
+
+/// Something that's still in the world, and isn't dead yet.
+pub fn is_alive(world: &EcsWorld, entity_id: EntityId) -> bool {
+    ecs_get!(world, entity_id, cur Health)
+        .map(|health| !health.is_dead())
+        .unwrap_or(false)
+}
+
+/// Returns the hits that actually did something.
+pub(crate) fn apply_damage(world: &EcsWorld, damage: &[Damage]) -> Vec<Damage> {
+    let mut landed = vec![];
+    for hit in damage.iter() {
+        let Some(mut health) = ecs_get!(world, hit.target, mut Health) else {
+            // it's not the kind of thing that can be hurt
+            continue;
+        };
+        if health.is_dead() {
+            // no kill stealing
+            continue;
+        }
+        if health.invulnerable {
+            // tink!
+            continue;
+        }
+        health.current -= hit.amount;
+        if hit.attacker.is_some() {
+            health.last_attacker = hit.attacker;
+        }
+        landed.push(hit.clone());
+    }
+    landed
+}
+
+/// Gets rid of everything that died this tick, and says so in `events`.
+pub(crate) fn handle_deaths(world: &mut EcsWorld, events: &mut Vec<GameEvent>) {
+    let mut dead = vec![];
+    for (entity_id, health, team, placement, visible, collider) in ecs_iter!(
+        world,
+        cur Health,
+        cur Option<Team>,
+        cur Option<Placement>,
+        cur Option<Visible>,
+        cur Option<Collider>
+    ) {
+        if !health.is_dead() {
+            continue;
+        }
+        let wreck = match (health.on_death, placement) {
+            (DeathBehavior::Wreck, Some(placement)) => {
+                Some((placement.clone(), visible.cloned(), collider.cloned()))
+            }
+            _ => None,
+        };
+        // the killer might not be around to ask by the time we're done
+        let killer_team = health
+            .last_attacker
+            .and_then(|killer| ecs_get!(world, killer, cur Team).copied())
+            .unwrap_or_default();
+        dead.push((
+            entity_id,
+            team.copied().unwrap_or_default(),
+            health.last_attacker,
+            killer_team,
+            wreck,
+        ));
+    }
+    for (entity_id, team, killer, killer_team, wreck) in dead {
+        world.despawn(entity_id);
+        // Wrecks look the same, and are still in the way, but that's it.
+        let wreck_id = wreck.map(|(placement, visible, collider)| {
+            let wreck = Wreck { was: entity_id };
+            match (visible, collider) {
+                (Some(visible), Some(collider)) => {
+                    ecs_spawn!(world, placement, wreck, visible, collider,)
+                }
+                (Some(visible), None) => ecs_spawn!(world, placement, wreck, visible,),
+                (None, Some(collider)) => ecs_spawn!(world, placement, wreck, collider,),
+                (None, None) => ecs_spawn!(world, placement, wreck,),
+            }
+        });
+        events.push(GameEvent::Death {
+            entity_id,
+            team,
+            killer,
+            killer_team,
+            wreck_id,
+        });
+    }
+}
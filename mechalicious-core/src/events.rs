@@ -0,0 +1,56 @@
+use super::*;
+
+/// Something that happened during a tick that the outside world (the client's
+/// kill feed, the server's logs, scoring...) might want to know about.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// `entity_id` is gone. If it left a wreck, that's `wreck_id`. The
+    /// teams are whoever they were on when it happened.
+    Death {
+        entity_id: EntityId,
+        team: Team,
+        killer: Option<EntityId>,
+        killer_team: Team,
+        wreck_id: Option<EntityId>,
+    },
+    /// A `CaptureZone` (a spawn point, the core, a resource node...)
+    /// changed hands.
+    Captured {
+        entity_id: EntityId,
+        old_owner: Team,
+        new_owner: Team,
+    },
+    Respawned {
+        player: PlayerId,
+        entity_id: EntityId,
+    },
+    /// `player` is ready to respawn, but can't. They'll stay in the queue.
+    RespawnRejected {
+        player: PlayerId,
+        reason: RespawnRejection,
+    },
+    /// A boss got hurt enough to change what it's doing. `phase` is an
+    /// index into its `Boss::phases`.
+    BossPhaseChanged { entity_id: EntityId, phase: usize },
+    UnitPurchased {
+        button: EntityId,
+        unit: EntityId,
+        team_id: TeamId,
+    },
+    /// Somebody pressed `button`, but didn't get anything.
+    PurchaseRejected {
+        button: EntityId,
+        team_id: TeamId,
+        reason: PurchaseRejection,
+    },
+    /// PVE: wave number `wave` is here. `units` came in with it, and the
+    /// defenders got `bonus`.
+    WaveStarted { wave: u32, units: usize, bonus: f32 },
+    /// The match moved on to `phase`.
+    PhaseChanged { phase: MatchPhase },
+    /// The match is over. See `GameWorld::get_outcome` for the details.
+    MatchDecided {
+        winner: Option<TeamId>,
+        reason: OutcomeReason,
+    },
+}
@@ -0,0 +1,213 @@
+use super::*;
+
+// Mechs and units closer than this make goats nervous.
+const GOAT_SCARE_RADIUS: f32 = 4.0;
+// So do bullets.
+const GOAT_GUNFIRE_RADIUS: f32 = 3.0;
+// How long a goat keeps running once it's been scared, in seconds.
+const GOAT_FLEE_TIME: f32 = 2.0;
+// How hard a goat tries to go, out of its full thrust, when it's just
+// wandering around.
+const GOAT_WANDER_EFFORT: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoatMood {
+    /// Standing still, eating.
+    Grazing,
+    /// Ambling along in some direction.
+    Wandering,
+    /// Running away from something.
+    Fleeing,
+}
+
+/// A goat. It isn't on anybody's team, it doesn't fight back, and it mostly
+/// wants to be left alone.
+#[derive(Clone, Debug)]
+pub struct Goat {
+    pub mood: GoatMood,
+    /// Until the goat gets bored of what it's doing.
+    pub time_left: f32, // seconds
+    /// Which way it's going (when it's going anywhere).
+    pub heading: Vector,
+    /// How much health it had last tick, so it notices getting shot.
+    pub last_health: f32,
+}
+
+pub(crate) fn spawn_goat(world: &mut EcsWorld, position: Point) -> EntityId {
+    ecs_spawn!(
+        world,
+        Placement {
+            position,
+            angle: 0.0,
+            scale: 0.25,
+        },
+        Physics {
+            mass: 0.3,
+            moment: 0.3,
+            force: vector![0.0, 0.0],
+            torque: 0.0,
+            velocity: vector![0.0, 0.0],
+            angular_velocity: 0.0,
+        },
+        Collider {
+            radius: 1.0,
+            restitution: 0.5,
+            friction: 0.2,
+        },
+        ShipControls::default(),
+        ShipControlCharacteristics {
+            thrust: 6.0,
+            turn_torque: 30.0,
+            ..Default::default()
+        },
+        Health::new(30.0, DeathBehavior::Despawn),
+        Goat {
+            mood: GoatMood::Grazing,
+            time_left: 1.0,
+            heading: vector![1.0, 0.0],
+            last_health: 30.0,
+        },
+        Visible {
+            model_path: "goat.v2d",
+        },
+    )
+}
+
+/// Decides what every goat is up to. Goats graze, wander off, graze some
+/// more, and run away from anything scary: mechs, units, bullets, or getting
+/// shot. Goats that got killed this tick pay out their bounty to the team
+/// that did it, if money's changing hands in this phase of the match.
+pub(crate) fn update_goats(
+    world: &EcsWorld,
+    level_geometry: &LevelGeometry,
+    spatial_hash: &SpatialHash,
+    dt: f32,
+) {
+    let bullets: Vec<Point> = ecs_iter!(world, cur Projectile, cur Placement)
+        .map(|(_entity_id, _projectile, placement)| placement.position)
+        .collect();
+    let bounty = ecs_singleton!(world, cur MatchSettings).goat_bounty;
+    let paying = ecs_singleton!(world, cur MatchClock)
+        .phase
+        .runs(GameSystem::Economy);
+    let rng = ecs_singleton!(world, mut GameRng);
+    for (entity_id, goat, placement, collider, health, controls) in ecs_iter!(
+        world,
+        mut Goat,
+        cur Placement,
+        cur Collider,
+        cur Health,
+        mut ShipControls
+    ) {
+        let position = placement.position;
+        if health.is_dead() {
+            let killer_team = health
+                .last_attacker
+                .and_then(|killer| ecs_get!(world, killer, cur Team).copied())
+                .and_then(|team| team.0);
+            if let (Some(team_id), true) = (killer_team, paying && bounty > 0.0) {
+                ecs_singleton!(world, mut ResourceBank).credit(
+                    team_id,
+                    bounty,
+                    TransactionReason::Bounty,
+                );
+            }
+            continue;
+        }
+        // What's scary, and where is it?
+        let mut threat = None;
+        if health.current < goat.last_health {
+            threat = health
+                .last_attacker
+                .and_then(|attacker| ecs_get!(world, attacker, cur Placement))
+                .map(|attacker| attacker.position);
+        }
+        goat.last_health = health.current;
+        if threat.is_none() {
+            threat = spatial_hash
+                .nearest(position, GOAT_SCARE_RADIUS, |entry| {
+                    entry.entity_id != entity_id
+                        && ecs_get!(world, entry.entity_id, cur Capturer).is_some()
+                })
+                .map(|entry| entry.position);
+        }
+        if threat.is_none() {
+            threat = bullets
+                .iter()
+                .find(|bullet| (**bullet - position).magnitude() <= GOAT_GUNFIRE_RADIUS)
+                .copied();
+        }
+        if let Some(threat) = threat {
+            let away = position - threat;
+            goat.heading = if away.magnitude_squared() > 0.0 {
+                away.normalize()
+            } else {
+                random_heading(rng)
+            };
+            goat.mood = GoatMood::Fleeing;
+            goat.time_left = GOAT_FLEE_TIME;
+        }
+        goat.time_left -= dt;
+        if goat.time_left <= 0.0 {
+            // Got bored (or calmed down). Go do the other thing.
+            match goat.mood {
+                GoatMood::Grazing => {
+                    goat.mood = GoatMood::Wandering;
+                    goat.heading = random_heading(rng);
+                    goat.time_left = rng.gen_range(1.0..3.0);
+                }
+                GoatMood::Wandering | GoatMood::Fleeing => {
+                    goat.mood = GoatMood::Grazing;
+                    goat.time_left = rng.gen_range(2.0..5.0);
+                }
+            }
+        }
+        // Don't walk into walls. Turn around, more or less.
+        let radius = collider.scaled_radius(placement);
+        if goat.mood != GoatMood::Grazing
+            && !level_geometry.line_of_sight(position, position + goat.heading * (radius + 0.5))
+        {
+            let turn = rng.gen_range(PI * 0.5..PI * 1.5);
+            goat.heading = vector![
+                goat.heading.x * turn.cos() - goat.heading.y * turn.sin(),
+                goat.heading.x * turn.sin() + goat.heading.y * turn.cos()
+            ];
+        }
+        let effort = match goat.mood {
+            GoatMood::Grazing => 0.0,
+            GoatMood::Wandering => GOAT_WANDER_EFFORT,
+            GoatMood::Fleeing => 1.0,
+        };
+        controls.movement = goat.heading * effort;
+        if effort > 0.0 {
+            controls.aim = goat.heading;
+        }
+        controls.fire = false;
+    }
+}
+
+fn random_heading(rng: &mut impl Rng) -> Vector {
+    let angle = rng.gen_range(0.0..TAU);
+    vector![angle.cos(), angle.sin()]
+}
+
+/// What a goat looks like from the outside.
+#[derive(Clone, Debug)]
+pub struct GoatInfo {
+    pub entity_id: EntityId,
+    pub position: Point,
+    pub mood: GoatMood,
+}
+
+impl GameWorld {
+    pub fn get_goats(&self) -> Vec<GoatInfo> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_iter!(world, cur Goat, cur Placement)
+            .map(|(entity_id, goat, placement)| GoatInfo {
+                entity_id,
+                position: placement.position,
+                mood: goat.mood,
+            })
+            .collect()
+    }
+}
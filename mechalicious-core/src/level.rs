@@ -0,0 +1,598 @@
+use super::*;
+
+// How many times we'll try to shove a body out of the walls in one tick. A
+// body wedged into a corner needs more than one push; a body that's still
+// stuck after this many is just going to have to wait until next tick.
+const MAX_WALL_PUSHES: usize = 4;
+
+/// A solid line. Nothing with a body gets to cross it.
+#[derive(Clone, Debug)]
+pub struct LineSegment {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl LineSegment {
+    pub fn new(start: Point, end: Point) -> LineSegment {
+        LineSegment { start, end }
+    }
+    /// The point on this segment that is closest to `point`.
+    pub fn closest_point(&self, point: Point) -> Point {
+        let along = self.end - self.start;
+        let length_squared = along.magnitude_squared();
+        if length_squared == 0.0 {
+            return self.start;
+        }
+        let theta = ((point - self.start).dot(&along) / length_squared).clamp(0.0, 1.0);
+        self.start + along * theta
+    }
+    /// Where the path from `from` to `to` crosses this segment, as a fraction
+    /// of the way along that path. `None` if it doesn't cross.
+    pub fn intersect_path(&self, from: Point, to: Point) -> Option<f32> {
+        let path = to - from;
+        let along = self.end - self.start;
+        let denominator = path.perp(&along);
+        if denominator == 0.0 {
+            // parallel (or degenerate), so no crossing worth caring about
+            return None;
+        }
+        let offset = self.start - from;
+        let path_theta = offset.perp(&along) / denominator;
+        let segment_theta = offset.perp(&path) / denominator;
+        if (0.0..=1.0).contains(&path_theta) && (0.0..=1.0).contains(&segment_theta) {
+            Some(path_theta)
+        } else {
+            None
+        }
+    }
+}
+
+/// The solid lines that make up a level.
+#[derive(Clone, Debug, Default)]
+pub struct LevelGeometry {
+    pub segments: Vec<LineSegment>,
+}
+
+impl LevelGeometry {
+    pub fn new(segments: Vec<LineSegment>) -> LevelGeometry {
+        LevelGeometry { segments }
+    }
+    /// Moves a circle that wants to go from `old_position` to `position` so
+    /// that it doesn't end up inside or on the other side of any wall. Any
+    /// part of `velocity` that points into a wall it touched gets removed,
+    /// so the circle slides along walls instead of sticking to them.
+    pub fn collide_circle(
+        &self,
+        old_position: Point,
+        position: &mut Point,
+        radius: f32,
+        velocity: &mut Vector,
+    ) {
+        // First, don't let anything tunnel straight through a wall in one
+        // step. Stop it at the wall, on the side it came from.
+        let mut earliest_hit: Option<(f32, &LineSegment)> = None;
+        for segment in self.segments.iter() {
+            if let Some(theta) = segment.intersect_path(old_position, *position) {
+                if earliest_hit.map(|(best, _)| theta < best).unwrap_or(true) {
+                    earliest_hit = Some((theta, segment));
+                }
+            }
+        }
+        if let Some((theta, segment)) = earliest_hit {
+            let hit_point = old_position + (*position - old_position) * theta;
+            let normal = segment_normal_facing(segment, old_position);
+            *position = hit_point + normal * radius;
+            remove_velocity_into(velocity, normal);
+        }
+        // Then, push it out of anything it's overlapping.
+        for _ in 0..MAX_WALL_PUSHES {
+            let mut pushed = false;
+            for segment in self.segments.iter() {
+                let closest = segment.closest_point(*position);
+                let offset = *position - closest;
+                let distance = offset.magnitude();
+                if distance >= radius {
+                    continue;
+                }
+                let normal = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    segment_normal_facing(segment, old_position)
+                };
+                *position = closest + normal * radius;
+                remove_velocity_into(velocity, normal);
+                pushed = true;
+            }
+            if !pushed {
+                break;
+            }
+        }
+    }
+    /// The first wall that a ray hits, if any: how far along the ray it
+    /// was, which segment it was, and the wall's normal (facing back toward
+    /// the ray's origin).
+    pub fn raycast(
+        &self,
+        origin: Point,
+        direction: Vector,
+        max_distance: f32,
+    ) -> Option<(f32, usize, Vector)> {
+        let end = origin + direction * max_distance;
+        let mut best: Option<(f32, usize)> = None;
+        for (index, segment) in self.segments.iter().enumerate() {
+            if let Some(theta) = segment.intersect_path(origin, end) {
+                if best
+                    .map(|(best_theta, _)| theta < best_theta)
+                    .unwrap_or(true)
+                {
+                    best = Some((theta, index));
+                }
+            }
+        }
+        best.map(|(theta, index)| {
+            (
+                theta * max_distance,
+                index,
+                segment_normal_facing(&self.segments[index], origin),
+            )
+        })
+    }
+    /// True if nothing solid is in the way between `a` and `b`.
+    pub fn line_of_sight(&self, a: Point, b: Point) -> bool {
+        !self
+            .segments
+            .iter()
+            .any(|segment| segment.intersect_path(a, b).is_some())
+    }
+}
+
+/// The unit normal of `segment` that points toward the side `point` is on.
+fn segment_normal_facing(segment: &LineSegment, point: Point) -> Vector {
+    let along = segment.end - segment.start;
+    let normal = vector![-along.y, along.x];
+    let normal = if normal.magnitude_squared() == 0.0 {
+        vector![1.0, 0.0]
+    } else {
+        normal.normalize()
+    };
+    if (point - segment.start).dot(&normal) < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+fn remove_velocity_into(velocity: &mut Vector, normal: Vector) {
+    let into_wall = velocity.dot(&normal);
+    if into_wall < 0.0 {
+        *velocity -= normal * into_wall;
+    }
+}
+
+/*
+
+Level files are plain text, one thing per line. Anything after a `#` is a
+comment. Coordinates are in world units, angles are in radians.
+
+    wall X1 Y1 X2 Y2 [X3 Y3 ...]       a chain of solid lines
+    loop X1 Y1 X2 Y2 X3 Y3 [...]       same, but the last point connects back
+                                       to the first
+    spawn X Y [TEAM]                   a team spawn point, optionally
+                                       starting out owned by TEAM
+    core X Y [RESOURCES]               the core (there can only be one),
+                                       optionally holding RESOURCES instead
+                                       of the usual amount
+    node TIER X Y [TEAM]               a resource node, optionally starting
+                                       out owned by TEAM
+    miniboss X Y                       a miniboss, guarding whichever node
+                                       is closest to it
+    button UNIT X Y FACTORY_X FACTORY_Y
+                                       a button that buys a UNIT, which
+                                       comes out at the factory. It belongs
+                                       to whoever owns the closest spawn
+                                       point
+    goat X Y [COUNT]                   a goat, or a herd of COUNT goats
+                                       milling around X Y
+    decoration MODEL X Y [ANGLE [SCALE]]
+    scatter COUNT MODEL MIN_X MIN_Y MAX_X MAX_Y SCALE
+                                       COUNT decorations, randomly placed
+                                       and rotated inside the given box
+
+*/
+
+#[derive(Clone, Debug)]
+pub struct LevelSpawnPoint {
+    pub position: Point,
+    pub team: Team,
+}
+
+#[derive(Clone, Debug)]
+pub struct LevelCore {
+    pub position: Point,
+    pub resources: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct LevelResourceNode {
+    pub position: Point,
+    pub tier: u32,
+    pub team: Team,
+}
+
+#[derive(Clone, Debug)]
+pub struct LevelButton {
+    pub unit_type: String,
+    pub position: Point,
+    pub factory: Point,
+}
+
+#[derive(Clone, Debug)]
+pub struct LevelDecoration {
+    pub model_path: String,
+    pub placement: Placement,
+}
+
+#[derive(Clone, Debug)]
+pub struct LevelScatter {
+    pub count: u32,
+    pub model_path: String,
+    pub min: Point,
+    pub max: Point,
+    pub scale: f32,
+}
+
+/// Everything that a level file says, before it gets turned into a world.
+#[derive(Clone, Debug, Default)]
+pub struct Level {
+    pub geometry: LevelGeometry,
+    pub spawn_points: Vec<LevelSpawnPoint>,
+    pub core: Option<LevelCore>,
+    pub resource_nodes: Vec<LevelResourceNode>,
+    pub miniboss_sites: Vec<Point>,
+    pub buttons: Vec<LevelButton>,
+    pub goats: Vec<Point>,
+    pub decorations: Vec<LevelDecoration>,
+    pub scatters: Vec<LevelScatter>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelParseError {
+    pub line_number: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for LevelParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line_number, self.message)
+    }
+}
+
+impl std::error::Error for LevelParseError {}
+
+/// Something `LevelLine` can read a number into. Infinity and NaN parse
+/// just fine as floats, but nothing in a level (or a unit file) makes sense
+/// with them in it.
+pub(crate) trait LevelNumber: std::str::FromStr {
+    fn is_finite(&self) -> bool {
+        true
+    }
+}
+
+impl LevelNumber for f32 {
+    fn is_finite(&self) -> bool {
+        f32::is_finite(*self)
+    }
+}
+
+impl LevelNumber for u32 {}
+
+impl LevelNumber for usize {}
+
+/// The words on one line of a level file (or any other file that works the
+/// same way), and where that line was.
+pub(crate) struct LevelLine<'a> {
+    pub line_number: usize,
+    pub command: &'a str,
+    words: std::vec::IntoIter<&'a str>,
+}
+
+impl<'a> LevelLine<'a> {
+    /// Splits `source` up into lines, leaving out comments and blank lines.
+    pub fn split(source: &'a str) -> impl Iterator<Item = LevelLine<'a>> {
+        source.lines().enumerate().filter_map(|(index, line)| {
+            let line = line.split('#').next().unwrap_or("");
+            let mut words = line.split_whitespace().collect::<Vec<_>>().into_iter();
+            let command = words.next()?;
+            Some(LevelLine {
+                line_number: index + 1,
+                command,
+                words,
+            })
+        })
+    }
+    pub fn error(&self, message: impl Into<String>) -> LevelParseError {
+        LevelParseError {
+            line_number: self.line_number,
+            message: message.into(),
+        }
+    }
+    pub fn next_word(&mut self, what: &str) -> Result<&'a str, LevelParseError> {
+        match self.words.next() {
+            Some(word) => Ok(word),
+            None => Err(self.error(format!("`{}` is missing {what}", self.command))),
+        }
+    }
+    pub fn next_number<T: LevelNumber>(&mut self, what: &str) -> Result<T, LevelParseError> {
+        let word = self.next_word(what)?;
+        match word.parse::<T>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(self.error(format!("expected {what} to be a number, not `{word}`"))),
+        }
+    }
+    /// Like `next_number`, for things that don't make sense unless they're
+    /// more than zero.
+    pub fn next_positive(&mut self, what: &str) -> Result<f32, LevelParseError> {
+        self.next_at_least_zero(what, false)
+    }
+    /// Like `next_number`, for things that can be zero, but not negative.
+    pub fn next_non_negative(&mut self, what: &str) -> Result<f32, LevelParseError> {
+        self.next_at_least_zero(what, true)
+    }
+    fn next_at_least_zero(
+        &mut self,
+        what: &str,
+        zero_is_fine: bool,
+    ) -> Result<f32, LevelParseError> {
+        let word = self.next_word(what)?;
+        match word.parse::<f32>() {
+            Ok(number) if number.is_finite() && (number > 0.0 || zero_is_fine && number == 0.0) => {
+                Ok(number)
+            }
+            Ok(number) if number.is_finite() => Err(self.error(if zero_is_fine {
+                format!("expected {what} to be zero or more, not `{word}`")
+            } else {
+                format!("expected {what} to be more than zero, not `{word}`")
+            })),
+            _ => Err(self.error(format!("expected {what} to be a number, not `{word}`"))),
+        }
+    }
+    pub fn next_point(&mut self) -> Result<Point, LevelParseError> {
+        let x = self.next_number("an X coordinate")?;
+        let y = self.next_number("a Y coordinate")?;
+        Ok(point![x, y])
+    }
+    pub fn optional_number<T: LevelNumber>(
+        &mut self,
+        what: &str,
+    ) -> Result<Option<T>, LevelParseError> {
+        if self.words.len() == 0 {
+            Ok(None)
+        } else {
+            self.next_number(what).map(Some)
+        }
+    }
+    pub fn points(&mut self, minimum: usize) -> Result<Vec<Point>, LevelParseError> {
+        if !self.words.len().is_multiple_of(2) {
+            return Err(self.error(format!(
+                "`{}` needs an even number of coordinates",
+                self.command
+            )));
+        }
+        let mut points = Vec::with_capacity(self.words.len() / 2);
+        while self.words.len() > 0 {
+            points.push(self.next_point()?);
+        }
+        if points.len() < minimum {
+            return Err(self.error(format!(
+                "`{}` needs at least {minimum} points",
+                self.command
+            )));
+        }
+        Ok(points)
+    }
+    pub fn finish(&self) -> Result<(), LevelParseError> {
+        if self.words.len() == 0 {
+            Ok(())
+        } else {
+            Err(self.error(format!("too many values for `{}`", self.command)))
+        }
+    }
+}
+
+impl Level {
+    pub fn parse(source: &str) -> Result<Level, LevelParseError> {
+        let mut level = Level::default();
+        // so we can complain about them if there turn out to be no nodes,
+        // or no spawn points
+        let mut first_miniboss_line = None;
+        let mut first_button_line = None;
+        for mut line in LevelLine::split(source) {
+            let command = line.command;
+            match command {
+                "wall" | "loop" => {
+                    let mut points = line.points(if command == "wall" { 2 } else { 3 })?;
+                    if command == "loop" {
+                        points.push(points[0]);
+                    }
+                    for pair in points.windows(2) {
+                        level
+                            .geometry
+                            .segments
+                            .push(LineSegment::new(pair[0], pair[1]));
+                    }
+                }
+                "spawn" => {
+                    let position = line.next_point()?;
+                    let team = Team(line.optional_number("a team")?.map(TeamId));
+                    level.spawn_points.push(LevelSpawnPoint { position, team });
+                }
+                "core" => {
+                    if level.core.is_some() {
+                        return Err(line.error("there can only be one core"));
+                    }
+                    let position = line.next_point()?;
+                    let resources = line
+                        .optional_number("an amount of resources")?
+                        .unwrap_or(DEFAULT_CORE_RESOURCES);
+                    level.core = Some(LevelCore {
+                        position,
+                        resources,
+                    });
+                }
+                "node" => {
+                    let tier = line.next_number("a tier")?;
+                    let position = line.next_point()?;
+                    let team = Team(line.optional_number("a team")?.map(TeamId));
+                    level.resource_nodes.push(LevelResourceNode {
+                        position,
+                        tier,
+                        team,
+                    });
+                }
+                "miniboss" => {
+                    first_miniboss_line.get_or_insert(line.line_number);
+                    level.miniboss_sites.push(line.next_point()?);
+                }
+                "button" => {
+                    first_button_line.get_or_insert(line.line_number);
+                    let unit_type = line.next_word("a unit type")?.to_string();
+                    let position = line.next_point()?;
+                    let factory = line.next_point()?;
+                    level.buttons.push(LevelButton {
+                        unit_type,
+                        position,
+                        factory,
+                    });
+                }
+                "goat" => {
+                    let position = line.next_point()?;
+                    let count: usize = line.optional_number("a count")?.unwrap_or(1);
+                    // a little spiral, so they don't start out on top of
+                    // each other
+                    for n in 0..count {
+                        let angle = n as f32 * 2.4;
+                        let distance = (n as f32).sqrt() * 0.6;
+                        level
+                            .goats
+                            .push(position + vector![angle.cos(), angle.sin()] * distance);
+                    }
+                }
+                "decoration" => {
+                    let model_path = line.next_word("a model")?.to_string();
+                    let position = line.next_point()?;
+                    let angle = line.optional_number("an angle")?.unwrap_or(0.0);
+                    let scale = line.optional_number("a scale")?.unwrap_or(1.0);
+                    level.decorations.push(LevelDecoration {
+                        model_path,
+                        placement: Placement {
+                            position,
+                            angle,
+                            scale,
+                        },
+                    });
+                }
+                "scatter" => {
+                    let count = line.next_number("a count")?;
+                    let model_path = line.next_word("a model")?.to_string();
+                    let min = line.next_point()?;
+                    let max = line.next_point()?;
+                    let scale = line.next_number("a scale")?;
+                    if min.x > max.x || min.y > max.y {
+                        return Err(line.error(
+                            "the box for `scatter` is backwards (the minimum corner \
+                             comes first, then the maximum)",
+                        ));
+                    }
+                    level.scatters.push(LevelScatter {
+                        count,
+                        model_path,
+                        min,
+                        max,
+                        scale,
+                    });
+                }
+                _ => return Err(line.error(format!("unknown command `{command}`"))),
+            }
+            line.finish()?;
+        }
+        if let Some(line_number) = first_miniboss_line {
+            if level.resource_nodes.is_empty() {
+                return Err(LevelParseError {
+                    line_number,
+                    message: "a miniboss needs a node to guard".to_string(),
+                });
+            }
+        }
+        if let Some(line_number) = first_button_line {
+            if level.spawn_points.is_empty() {
+                return Err(LevelParseError {
+                    line_number,
+                    message: "a button needs a spawn point to belong to".to_string(),
+                });
+            }
+        }
+        Ok(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(source: &str) -> String {
+        Level::parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn unknown_commands() {
+        assert_eq!(
+            parse_error("wall 0 0 1 1\n\nbogus 1 2"),
+            "line 3: unknown command `bogus`"
+        );
+    }
+
+    #[test]
+    fn missing_numbers() {
+        assert_eq!(
+            parse_error("# a comment\nspawn 1"),
+            "line 2: `spawn` is missing a Y coordinate"
+        );
+        assert_eq!(
+            parse_error("spawn 0 0\nnode"),
+            "line 2: `node` is missing a tier"
+        );
+    }
+
+    #[test]
+    fn numbers_have_to_be_finite() {
+        assert_eq!(
+            parse_error("wall 0 0 NaN 1"),
+            "line 1: expected an X coordinate to be a number, not `NaN`"
+        );
+        assert_eq!(
+            parse_error("spawn 0 0\nspawn 0 -inf"),
+            "line 2: expected a Y coordinate to be a number, not `-inf`"
+        );
+        assert_eq!(
+            parse_error("spawn 0 0\nscatter 5 rock.v2d -1 -1 inf 1 0.1"),
+            "line 2: expected an X coordinate to be a number, not `inf`"
+        );
+    }
+
+    #[test]
+    fn scatter_boxes_go_min_then_max() {
+        assert_eq!(
+            parse_error("spawn 0 0\nscatter 5 rock.v2d 1 -1 -1 1 0.1"),
+            "line 2: the box for `scatter` is backwards (the minimum corner comes \
+             first, then the maximum)"
+        );
+        assert_eq!(
+            parse_error("scatter 5 rock.v2d -1 1 1 -1 0.1"),
+            "line 1: the box for `scatter` is backwards (the minimum corner comes \
+             first, then the maximum)"
+        );
+        let level = Level::parse("spawn 0 0\nscatter 5 rock.v2d -1 -2 1 2 0.1").unwrap();
+        assert_eq!(level.scatters[0].min, point![-1.0, -2.0]);
+        assert_eq!(level.scatters[0].max, point![1.0, 2.0]);
+    }
+}
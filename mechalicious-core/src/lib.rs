@@ -32,9 +32,72 @@ use rand::prelude::*;
 pub mod pid;
 use pid::*;
 
+pub mod rng;
+use rng::*;
+
 pub mod components;
 use components::*;
 
+pub mod level;
+use level::*;
+
+mod collision;
+
+pub mod spatial;
+use spatial::*;
+
+pub mod raycast;
+use raycast::*;
+
+mod weapons;
+
+pub mod teams;
+
+pub mod bank;
+use bank::*;
+
+mod capture;
+
+pub mod reservoir;
+
+pub mod boss;
+use boss::*;
+
+pub mod units;
+use units::*;
+
+mod unit_ai;
+
+pub mod goats;
+use goats::*;
+
+pub mod nav;
+use nav::*;
+
+pub mod purchase;
+use purchase::*;
+
+pub mod waves;
+use waves::*;
+
+pub mod respawn;
+use respawn::*;
+
+pub mod damage;
+use damage::*;
+
+pub mod events;
+use events::*;
+
+pub mod outcome;
+use outcome::*;
+
+pub mod mode;
+use mode::*;
+
+pub mod lifecycle;
+use lifecycle::*;
+
 mod systems;
 
 pub fn angle_subtract(a: f32, b: f32) -> f32 {
@@ -62,93 +125,209 @@ pub fn lerp<T: Mul<f32, Output = T> + Add<T, Output = T>>(a: T, b: T, theta: f32
 pub struct GameWorld {
     prev_ecs_world: Arcow<EcsWorld>,
     ecs_world: Arcow<EcsWorld>,
+    level_geometry: LevelGeometry,
+    navigation: Navigation,
+    spatial_hash: SpatialHash,
+    events: Vec<GameEvent>,
+    transactions: TransactionLog,
 }
 
 impl GameWorld {
-    pub fn new_test_world() -> GameWorld {
+    pub fn load_level(level_source: &str) -> Result<GameWorld, LevelParseError> {
+        GameWorld::load_level_seeded(level_source, DEFAULT_SEED)
+    }
+    /// Same seed, same level, same inputs: same game.
+    pub fn load_level_seeded(level_source: &str, seed: u64) -> Result<GameWorld, LevelParseError> {
+        Ok(GameWorld::from_level_seeded(
+            &Level::parse(level_source)?,
+            seed,
+        ))
+    }
+    pub fn from_level(level: &Level) -> GameWorld {
+        GameWorld::from_level_seeded(level, DEFAULT_SEED)
+    }
+    pub fn from_level_seeded(level: &Level, seed: u64) -> GameWorld {
+        let mut rng = GameRng::seed_from_u64(seed);
         let mut ecs_world = EcsWorld::with_blank_schema();
-        ecs_spawn!(ecs_world, WorldPhysics { air_thickness: 5.4 },);
-        ecs_spawn!(
-            ecs_world,
-            Placement {
-                position: point![-1.0, -1.0],
-                angle: 7.0,
-                scale: 0.3,
-            },
-            Physics {
-                mass: 1.0,
-                moment: 1.0,
-                force: vector![0.0, 0.0],
-                torque: 0.0,
-                velocity: vector![0.01, 0.01],
-                angular_velocity: 0.0,
-            },
-            ShipControls {
-                movement: vector![0.0, 0.0],
-                aim: vector![0.0, 0.0],
-                fire: false,
-            },
-            ShipControlCharacteristics::default(),
-            Visible {
-                model_path: "mechalicious.v2d",
-            },
-        );
-        ecs_spawn!(
-            ecs_world,
-            Placement {
-                position: point![0.0, 0.0],
-                angle: 7.0,
-                scale: 0.3,
-            },
-            Physics {
-                mass: 1.0,
-                moment: 1.0,
-                force: vector![0.0, 0.0],
-                torque: 0.0,
-                velocity: vector![0.0, 0.0],
-                angular_velocity: 0.0,
-            },
-            ShipControls {
-                movement: vector![0.0, 0.0],
-                aim: vector![0.0, 0.0],
-                fire: false,
-            },
-            ShipControlCharacteristics::default(),
-            Visible {
-                model_path: "mechalicious.v2d",
-            },
-        );
-        let mut thread_rng = thread_rng();
-        for _ in 0..600 {
-            let x = thread_rng.gen_range(-10.0..10.0);
-            let y = thread_rng.gen_range(-10.0..10.0);
+        ecs_spawn!(ecs_world, WorldPhysics::default(),);
+        ecs_spawn!(ecs_world, MatchSettings::default(),);
+        ecs_spawn!(ecs_world, ResourceBank::default(),);
+        ecs_spawn!(ecs_world, Players::default(),);
+        ecs_spawn!(ecs_world, RespawnQueue::default(),);
+        ecs_spawn!(ecs_world, UnitTypes::default(),);
+        ecs_spawn!(ecs_world, MatchResult::default(),);
+        ecs_spawn!(ecs_world, MatchClock::default(),);
+        ecs_spawn!(ecs_world, WaveDirector::default(),);
+        let mut spawn_point_ids = vec![];
+        for spawn_point in level.spawn_points.iter() {
+            let spawn_point_id = ecs_spawn!(
+                ecs_world,
+                Placement {
+                    position: spawn_point.position,
+                    angle: 0.0,
+                    scale: 1.0,
+                },
+                SpawnPoint {},
+                spawn_point.team,
+                CaptureZone::new(2.0, 5.0, CapturerKind::Mech),
+            );
+            spawn_point_ids.push((spawn_point_id, spawn_point.position));
+        }
+        if let Some(core) = level.core.as_ref() {
+            let core_id = ecs_spawn!(
+                ecs_world,
+                Placement {
+                    position: core.position,
+                    angle: 0.0,
+                    scale: 1.0,
+                },
+                Core {
+                    resources: core.resources,
+                },
+                Team::NEUTRAL,
+                CaptureZone::new(4.0, 10.0, CapturerKind::Mech),
+            );
+            spawn_guardian(&mut ecs_world, core.position, core_id);
+        }
+        let mut node_ids = vec![];
+        for resource_node in level.resource_nodes.iter() {
+            let node_id = ecs_spawn!(
+                ecs_world,
+                Placement {
+                    position: resource_node.position,
+                    angle: 0.0,
+                    scale: 1.0,
+                },
+                ResourceNode {
+                    tier: resource_node.tier,
+                },
+                resource_node.team,
+                // only attack units can take nodes
+                CaptureZone::new(2.5, 8.0, CapturerKind::Unit),
+            );
+            node_ids.push((node_id, resource_node.position));
+        }
+        // Each miniboss guards whichever node is closest to it. (The level
+        // parser already made sure there is one.)
+        for position in level.miniboss_sites.iter() {
+            let Some(node_id) = closest(&node_ids, *position) else {
+                continue;
+            };
+            spawn_miniboss(&mut ecs_world, *position, Team::NEUTRAL, node_id);
+        }
+        // Each button belongs to the closest spawn point. (The level parser
+        // already made sure there is one.)
+        for button in level.buttons.iter() {
+            let Some(base) = closest(&spawn_point_ids, button.position) else {
+                continue;
+            };
             ecs_spawn!(
                 ecs_world,
                 Placement {
-                    position: point![x, y],
-                    angle: thread_rng.gen_range(0.0..TAU),
-                    scale: 0.1,
+                    position: button.position,
+                    angle: 0.0,
+                    scale: 0.75,
                 },
+                PurchaseButton::new(button.unit_type.clone(), button.factory, base),
                 Visible {
-                    model_path: "mechalicious.v2d",
+                    model_path: "button.v2d",
                 },
             );
         }
+        for position in level.goats.iter() {
+            spawn_goat(&mut ecs_world, *position);
+        }
+        for decoration in level.decorations.iter() {
+            ecs_spawn!(
+                ecs_world,
+                decoration.placement.clone(),
+                Visible {
+                    model_path: intern_model_path(&decoration.model_path),
+                },
+            );
+        }
+        for scatter in level.scatters.iter() {
+            let model_path = intern_model_path(&scatter.model_path);
+            for _ in 0..scatter.count {
+                let x = rng.gen_range(scatter.min.x..=scatter.max.x);
+                let y = rng.gen_range(scatter.min.y..=scatter.max.y);
+                ecs_spawn!(
+                    ecs_world,
+                    Placement {
+                        position: point![x, y],
+                        angle: rng.gen_range(0.0..TAU),
+                        scale: scatter.scale,
+                    },
+                    Visible { model_path },
+                );
+            }
+        }
+        // whatever the scatters didn't use, the simulation gets
+        ecs_spawn!(ecs_world, rng,);
         let ecs_world = Arcow::new(ecs_world);
         GameWorld {
             prev_ecs_world: ecs_world.clone(),
             ecs_world,
+            level_geometry: level.geometry.clone(),
+            navigation: Navigation::default(),
+            spatial_hash: SpatialHash::default(),
+            events: vec![],
+            transactions: TransactionLog::default(),
         }
     }
+    /// Changes how much time goes by in one `tick`. The physics takes care of
+    /// itself; you just have to call `tick` this many times per second. Zero
+    /// counts as one.
+    pub fn set_tick_rate(&mut self, ticks_per_second: u32) {
+        self.ecs_world = self.ecs_world.buffered_tick(|world| {
+            ecs_singleton!(world, mut WorldPhysics).dt = 1.0 / ticks_per_second.max(1) as f32;
+        });
+    }
+    pub fn set_physics_substeps(&mut self, substeps: u32) {
+        self.ecs_world = self.ecs_world.buffered_tick(|world| {
+            ecs_singleton!(world, mut WorldPhysics).substeps = substeps.max(1);
+        });
+    }
+    pub fn set_match_settings(&mut self, match_settings: MatchSettings) {
+        self.ecs_world = self.ecs_world.buffered_tick(|world| {
+            ecs_singleton!(world, mut MatchSettings).clone_from(&match_settings);
+        });
+    }
     pub fn get_ecs_world(&self) -> &EcsWorld {
         &self.ecs_world
     }
+    pub fn get_level_geometry(&self) -> &LevelGeometry {
+        &self.level_geometry
+    }
+    /// Where everything was partway through the last tick: after it moved,
+    /// but before collisions pushed things apart. Close enough for looking
+    /// around, but ask the world if you need to know exactly where
+    /// something is.
+    pub fn get_spatial_hash(&self) -> &SpatialHash {
+        &self.spatial_hash
+    }
+    /// Everything that happened during the most recent tick.
+    pub fn get_events(&self) -> &[GameEvent] {
+        &self.events
+    }
     pub fn with_ecs_world(&mut self, handler: impl FnOnce(&EcsWorld)) {
         self.ecs_world
             .with_origin(self.prev_ecs_world.clone(), |x| handler(x))
     }
 }
 
+// Whichever of `candidates` is closest to `position`.
+fn closest(candidates: &[(EntityId, Point)], position: Point) -> Option<EntityId> {
+    candidates
+        .iter()
+        .min_by(|(_, a), (_, b)| {
+            (a - position)
+                .magnitude_squared()
+                .total_cmp(&(b - position).magnitude_squared())
+        })
+        .map(|(entity_id, _)| *entity_id)
+}
+
 pub fn similarity_to_transform(similarity: Similarity) -> Transform {
     Transform::from_matrix_unchecked(similarity.to_homogeneous())
 }
@@ -0,0 +1,112 @@
+use super::*;
+
+/// Where a match is, from start to finish.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchPhase {
+    /// Not enough players yet. You can fly around, but that's it.
+    #[default]
+    WaitingForPlayers,
+    /// Everybody's here. Get ready...
+    Countdown,
+    InProgress,
+    /// Time's up, but nobody's won yet. A little longer, then whoever's
+    /// ahead wins.
+    Overtime,
+    /// It's over. See `GameWorld::get_outcome`.
+    PostGame,
+}
+
+/// The parts of a tick that only happen in some phases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameSystem {
+    /// Players' inputs get through to their mechs.
+    Controls,
+    /// Shooting, getting shot, and dying.
+    Combat,
+    /// Bosses, attack units, and whatever the game mode sends in.
+    Ai,
+    Capture,
+    /// Money coming in, and buying things with it.
+    Economy,
+    Respawn,
+    /// Keeping score, and deciding who won.
+    Outcome,
+}
+
+impl MatchPhase {
+    /// True if `system` runs during this phase. Everything else (physics,
+    /// goats...) runs all the time.
+    pub fn runs(self, system: GameSystem) -> bool {
+        match self {
+            MatchPhase::WaitingForPlayers => {
+                matches!(system, GameSystem::Controls | GameSystem::Respawn)
+            }
+            MatchPhase::Countdown => matches!(system, GameSystem::Respawn),
+            MatchPhase::InProgress | MatchPhase::Overtime => true,
+            // stray bullets still land, but that's all
+            MatchPhase::PostGame => matches!(system, GameSystem::Combat),
+        }
+    }
+}
+
+/// Which phase the match is in, and for how much longer.
+#[derive(Clone, Debug, Default)]
+pub struct MatchClock {
+    pub phase: MatchPhase,
+    /// Until this phase is over. `None` if it doesn't end on its own.
+    pub time_left: Option<f32>, // seconds
+}
+
+/// Moves the match along to whatever phase it should be in now. A phase
+/// with nothing to wait for gets skipped straight through, so with the
+/// default settings, a match is in progress from the very first tick.
+pub(crate) fn update_phase(world: &EcsWorld, dt: f32, events: &mut Vec<GameEvent>) {
+    let match_settings = ecs_singleton!(world, cur MatchSettings).clone();
+    let player_count = ecs_singleton!(world, cur Players).iter().count();
+    let decided = ecs_singleton!(world, cur MatchResult).outcome.is_some();
+    let clock = ecs_singleton!(world, mut MatchClock);
+    // only the phase we started in gets to count down this tick
+    let mut dt = dt;
+    loop {
+        if let Some(time_left) = clock.time_left.as_mut() {
+            *time_left = (*time_left - std::mem::take(&mut dt)).max(0.0);
+        }
+        let times_up = clock.time_left == Some(0.0);
+        let next = match clock.phase {
+            MatchPhase::WaitingForPlayers => (player_count >= match_settings.min_players)
+                .then_some((MatchPhase::Countdown, Some(match_settings.countdown))),
+            MatchPhase::Countdown => {
+                times_up.then_some((MatchPhase::InProgress, match_settings.time_limit))
+            }
+            MatchPhase::InProgress | MatchPhase::Overtime if decided => {
+                Some((MatchPhase::PostGame, Some(match_settings.post_game)))
+            }
+            MatchPhase::InProgress => {
+                times_up.then_some((MatchPhase::Overtime, Some(match_settings.overtime)))
+            }
+            MatchPhase::Overtime if times_up => {
+                outcome::decide(world, most_kills(world), OutcomeReason::TimeUp, events);
+                Some((MatchPhase::PostGame, Some(match_settings.post_game)))
+            }
+            MatchPhase::Overtime | MatchPhase::PostGame => None,
+        };
+        let Some((phase, time_left)) = next else {
+            break;
+        };
+        clock.phase = phase;
+        clock.time_left = time_left;
+        events.push(GameEvent::PhaseChanged { phase });
+    }
+}
+
+impl GameWorld {
+    pub fn get_match_phase(&self) -> MatchPhase {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_singleton!(world, cur MatchClock).phase
+    }
+    /// Until the current phase is over, if it ends on its own.
+    pub fn get_phase_time_left(&self) -> Option<f32> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_singleton!(world, cur MatchClock).time_left
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use super::*;
+
+/// The rules that are different from one kind of match to another: where
+/// players respawn, where the money comes from, whose side the core is on,
+/// and how somebody wins or loses. Everything else is the same for every
+/// mode.
+///
+/// The defaults are the PvP rules, so a new mode only has to override what
+/// it does differently. Put it in `MatchSettings::mode`.
+pub trait GameMode: Debug + Send + Sync {
+    fn name(&self) -> &str;
+    /// Where each team's players come back.
+    fn spawn_points(&self, world: &EcsWorld) -> BTreeMap<TeamId, Point> {
+        owned_spawn_points(world)
+    }
+    /// Pays everybody for this tick.
+    fn update_income(&self, world: &EcsWorld, dt: f32) {
+        reservoir::drain_cores(world, dt);
+        reservoir::siphon_nodes(world, dt);
+    }
+    /// True if `team_id` can count on more money turning up later, even if
+    /// it's broke right now.
+    fn has_income(&self, world: &EcsWorld, team_id: TeamId) -> bool {
+        reservoir::has_income(world, team_id)
+    }
+    /// Anything else the mode does every tick, like handing the core to
+    /// somebody when the match starts, or sending in enemies. Doesn't run
+    /// once the match is decided.
+    fn update(&self, _world: &mut EcsWorld, _dt: f32, _events: &mut Vec<GameEvent>) {}
+    /// Who won, and why, if it's over. Once this says it's over, it's over.
+    fn decide(&self, world: &EcsWorld) -> Option<(Option<TeamId>, OutcomeReason)> {
+        last_team_standing(world)
+    }
+}
+
+/// Teams fight each other over the core and the nodes. Last one standing
+/// wins.
+#[derive(Clone, Debug, Default)]
+pub struct PvP;
+
+impl GameMode for PvP {
+    fn name(&self) -> &str {
+        "pvp"
+    }
+}
+
+/// The defenders hold the core against wave after wave of the attackers'
+/// units. The core is the defenders' spawn point, its guardian is on their
+/// side, and they get paid at the start of every wave instead of by the
+/// core and the nodes. If they lose the core, they lose.
+#[derive(Clone, Debug)]
+pub struct PvE {
+    pub defenders: TeamId,
+    pub attackers: TeamId,
+    pub waves: WaveSettings,
+}
+
+impl PvE {
+    pub fn new(defenders: TeamId, attackers: TeamId) -> PvE {
+        PvE {
+            defenders,
+            attackers,
+            waves: WaveSettings::default(),
+        }
+    }
+}
+
+impl GameMode for PvE {
+    fn name(&self) -> &str {
+        "pve"
+    }
+    fn spawn_points(&self, world: &EcsWorld) -> BTreeMap<TeamId, Point> {
+        let mut spawn_points = owned_spawn_points(world);
+        for (_entity_id, placement, _core, team) in
+            ecs_iter!(world, cur Placement, cur Core, cur Team)
+        {
+            if *team == Team::of(self.defenders) {
+                spawn_points.insert(self.defenders, placement.position);
+            }
+        }
+        spawn_points
+    }
+    // The money comes in with the waves instead.
+    fn update_income(&self, _world: &EcsWorld, _dt: f32) {}
+    // There's always another wave coming.
+    fn has_income(&self, world: &EcsWorld, team_id: TeamId) -> bool {
+        team_id == self.defenders || reservoir::has_income(world, team_id)
+    }
+    fn update(&self, world: &mut EcsWorld, dt: f32, events: &mut Vec<GameEvent>) {
+        waves::update_waves(world, self, dt, events);
+    }
+    fn decide(&self, world: &EcsWorld) -> Option<(Option<TeamId>, OutcomeReason)> {
+        let core_lost = ecs_iter!(world, cur Core, cur Team)
+            .any(|(_entity_id, _core, team)| *team != Team::of(self.defenders));
+        if core_lost {
+            return Some((None, OutcomeReason::CoreLost));
+        }
+        last_team_standing(world)
+    }
+}
+
+/// The mode for a match that doesn't say otherwise.
+pub fn default_mode() -> Arc<dyn GameMode> {
+    Arc::new(PvP)
+}
@@ -0,0 +1,357 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+
+use super::*;
+
+// How many points we put around each corner of the level.
+const POINTS_PER_CORNER: usize = 8;
+// Agent radii get rounded up to a multiple of this, so that things that are
+// nearly the same size can share a graph.
+const RADIUS_GRANULARITY: f32 = 0.125;
+
+/// A visibility graph for things of one size: points just off every corner
+/// of the level, and which of them can get straight to which others without
+/// touching a wall.
+#[derive(Clone, Debug)]
+pub struct NavGraph {
+    pub agent_radius: f32,
+    pub points: Vec<Point>,
+    /// For each point, the points it can get straight to, and how far away
+    /// they are.
+    pub edges: Vec<Vec<(usize, f32)>>,
+}
+
+impl NavGraph {
+    pub fn bake(level_geometry: &LevelGeometry, agent_radius: f32) -> NavGraph {
+        // All the corners, without doubles where walls meet.
+        let mut corners: Vec<Point> = vec![];
+        for segment in level_geometry.segments.iter() {
+            for end in [segment.start, segment.end] {
+                if !corners
+                    .iter()
+                    .any(|corner| (corner - end).magnitude_squared() < 1e-6)
+                {
+                    corners.push(end);
+                }
+            }
+        }
+        // A ring of points around each corner. Only keep the ones that an
+        // agent could actually stand on.
+        let offset = agent_radius * 1.5 + 0.1;
+        let mut points = vec![];
+        for corner in corners {
+            for n in 0..POINTS_PER_CORNER {
+                let angle = n as f32 * TAU / POINTS_PER_CORNER as f32;
+                let point = corner + vector![angle.cos(), angle.sin()] * offset;
+                if distance_to_walls(level_geometry, point) >= agent_radius {
+                    points.push(point);
+                }
+            }
+        }
+        let mut edges = vec![vec![]; points.len()];
+        for a in 0..points.len() {
+            for b in a + 1..points.len() {
+                if is_clear(level_geometry, points[a], points[b], agent_radius) {
+                    let distance = (points[b] - points[a]).magnitude();
+                    edges[a].push((b, distance));
+                    edges[b].push((a, distance));
+                }
+            }
+        }
+        NavGraph {
+            agent_radius,
+            points,
+            edges,
+        }
+    }
+    /// The shortest way from `from` to `to`, as a list of points to head for
+    /// in order (not including `from`, but including `to`). `None` if there's
+    /// no way to get there.
+    pub fn find_path(
+        &self,
+        level_geometry: &LevelGeometry,
+        from: Point,
+        to: Point,
+    ) -> Option<Vec<Point>> {
+        let radius = self.agent_radius;
+        if is_clear(level_geometry, from, to, radius) {
+            return Some(vec![to]);
+        }
+        // Agents tend to be right up against a wall. Don't hold it against
+        // them when getting started.
+        let start_radius = radius * 0.5;
+        // A* over the graph. `from` and `to` are two extra nodes on the end.
+        let start = self.points.len();
+        let goal = start + 1;
+        let position = |index: usize| match index {
+            x if x == start => from,
+            x if x == goal => to,
+            x => self.points[x],
+        };
+        let mut best_cost = vec![f32::INFINITY; self.points.len() + 2];
+        let mut came_from = vec![usize::MAX; self.points.len() + 2];
+        let mut open = BinaryHeap::new();
+        best_cost[start] = 0.0;
+        open.push(OpenNode {
+            estimate: (to - from).magnitude(),
+            cost: 0.0,
+            index: start,
+        });
+        while let Some(OpenNode { cost, index, .. }) = open.pop() {
+            if cost > best_cost[index] {
+                // we found a better way here after this went in
+                continue;
+            }
+            if index == goal {
+                let mut path = vec![];
+                let mut index = goal;
+                while index != start {
+                    path.push(position(index));
+                    index = came_from[index];
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let here = position(index);
+            let mut neighbors: Vec<(usize, f32)> = if index == start {
+                (0..self.points.len())
+                    .filter(|&x| is_clear(level_geometry, from, self.points[x], start_radius))
+                    .map(|x| (x, (self.points[x] - from).magnitude()))
+                    .collect()
+            } else {
+                self.edges[index].clone()
+            };
+            if index != start && is_clear(level_geometry, here, to, radius) {
+                neighbors.push((goal, (to - here).magnitude()));
+            }
+            for (neighbor, distance) in neighbors {
+                let cost = best_cost[index] + distance;
+                if cost < best_cost[neighbor] {
+                    best_cost[neighbor] = cost;
+                    came_from[neighbor] = index;
+                    open.push(OpenNode {
+                        estimate: cost + (to - position(neighbor)).magnitude(),
+                        cost,
+                        index: neighbor,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+// Something in A*'s open set. `BinaryHeap` pops the biggest thing first, so
+// this is ordered backwards.
+#[derive(PartialEq)]
+struct OpenNode {
+    estimate: f32,
+    // how far it was from the start when it went in
+    cost: f32,
+    index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn distance_to_walls(level_geometry: &LevelGeometry, point: Point) -> f32 {
+    level_geometry
+        .segments
+        .iter()
+        .map(|segment| (segment.closest_point(point) - point).magnitude())
+        .fold(f32::INFINITY, f32::min)
+}
+
+// True if a circle of `radius` can slide from `a` to `b` without touching
+// any walls.
+fn is_clear(level_geometry: &LevelGeometry, a: Point, b: Point, radius: f32) -> bool {
+    let path = LineSegment::new(a, b);
+    level_geometry.segments.iter().all(|segment| {
+        if segment.intersect_path(a, b).is_some() {
+            return false;
+        }
+        let distance = [
+            (path.closest_point(segment.start) - segment.start).magnitude(),
+            (path.closest_point(segment.end) - segment.end).magnitude(),
+            (segment.closest_point(a) - a).magnitude(),
+            (segment.closest_point(b) - b).magnitude(),
+        ]
+        .into_iter()
+        .fold(f32::INFINITY, f32::min);
+        // a little slack, since things get pushed out to exactly `radius`
+        distance >= radius * 0.99
+    })
+}
+
+/// Nav graphs for every size of agent that's asked for one, baked the first
+/// time they're needed. They're thrown out whenever the level changes.
+#[derive(Debug, Default)]
+pub struct Navigation {
+    graphs: Mutex<HashMap<u32, Arc<NavGraph>>>,
+    // goes up every time the graphs get thrown out
+    revision: AtomicU64,
+}
+
+impl Navigation {
+    pub fn graph(&self, level_geometry: &LevelGeometry, agent_radius: f32) -> Arc<NavGraph> {
+        let steps = (agent_radius / RADIUS_GRANULARITY).ceil().max(0.0) as u32;
+        let mut graphs = self.graphs.lock().unwrap();
+        graphs
+            .entry(steps)
+            .or_insert_with(|| {
+                Arc::new(NavGraph::bake(
+                    level_geometry,
+                    steps as f32 * RADIUS_GRANULARITY,
+                ))
+            })
+            .clone()
+    }
+    /// See `NavGraph::find_path`.
+    pub fn find_path(
+        &self,
+        level_geometry: &LevelGeometry,
+        from: Point,
+        to: Point,
+        agent_radius: f32,
+    ) -> Option<Vec<Point>> {
+        self.graph(level_geometry, agent_radius)
+            .find_path(level_geometry, from, to)
+    }
+    /// Which version of the level the graphs are for. Paths found with an
+    /// older revision might go through walls that weren't there before.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(AtomicOrdering::Relaxed)
+    }
+    /// Throws out all the graphs, so they get baked again next time.
+    pub fn invalidate(&self) {
+        self.graphs.lock().unwrap().clear();
+        self.revision.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+}
+
+impl GameWorld {
+    /// How something `agent_radius` big can get from `from` to `to` without
+    /// running into walls. See `NavGraph::find_path`.
+    pub fn find_path(&self, from: Point, to: Point, agent_radius: f32) -> Option<Vec<Point>> {
+        self.navigation
+            .find_path(&self.level_geometry, from, to, agent_radius)
+    }
+    /// Swaps out the level's walls. Anything that depended on the old ones
+    /// (like the nav graphs) gets redone.
+    pub fn set_level_geometry(&mut self, level_geometry: LevelGeometry) {
+        self.level_geometry = level_geometry;
+        self.navigation.invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOX: &str = "loop -20 -20 20 -20 20 20 -20 20\n";
+
+    fn path_length(from: Point, path: &[Point]) -> f32 {
+        let mut here = from;
+        let mut length = 0.0;
+        for waypoint in path {
+            length += (waypoint - here).magnitude();
+            here = *waypoint;
+        }
+        length
+    }
+
+    // Every leg of the path has room for something `radius` big.
+    fn assert_clear(level_geometry: &LevelGeometry, from: Point, path: &[Point], radius: f32) {
+        let mut here = from;
+        for waypoint in path {
+            assert!(
+                is_clear(level_geometry, here, *waypoint, radius),
+                "{here} to {waypoint} in {path:?}"
+            );
+            here = *waypoint;
+        }
+    }
+
+    #[test]
+    fn goes_around_walls() {
+        let world = GameWorld::load_level(&format!("{BOX}wall 0 -5 0 5")).unwrap();
+        let (from, to) = (point![-3.0, 0.0], point![3.0, 0.0]);
+        let path = world.find_path(from, to, 0.5).unwrap();
+        assert!(path.len() >= 2, "{path:?}");
+        assert_eq!(path.last(), Some(&to));
+        // not a straight line, but not a scenic route either
+        let length = path_length(from, &path);
+        assert!(length > 6.0 && length < 14.0, "{length}");
+        // nothing in the way? straight there
+        assert_eq!(
+            world.find_path(from, point![-3.0, 2.0], 0.5),
+            Some(vec![point![-3.0, 2.0]])
+        );
+    }
+
+    #[test]
+    fn big_things_keep_away_from_corners() {
+        let world = GameWorld::load_level(&format!("{BOX}wall 0 -5 0 5\nwall 4 -3 4 3")).unwrap();
+        let (from, to) = (point![-3.0, 0.0], point![8.0, 0.0]);
+        for radius in [0.25, 0.5, 1.0] {
+            let path = world.find_path(from, to, radius).unwrap();
+            // the first leg gets a little slack, since agents start out
+            // right up against walls
+            let (first, rest) = path.split_first().unwrap();
+            assert_clear(world.get_level_geometry(), from, &[*first], radius * 0.5);
+            assert_clear(world.get_level_geometry(), *first, rest, radius);
+        }
+        // A gap that's big enough for small things, but not big ones.
+        let world =
+            GameWorld::load_level(&format!("{BOX}wall 0 -20 0 -0.6\nwall 0 0.6 0 20")).unwrap();
+        let (from, to) = (point![-3.0, 0.0], point![3.0, 0.0]);
+        assert!(world.find_path(from, to, 0.25).is_some());
+        assert!(world.find_path(from, to, 1.0).is_none());
+    }
+
+    #[test]
+    fn no_way_out() {
+        let world = GameWorld::load_level(&format!("{BOX}loop -2 -2 2 -2 2 2 -2 2")).unwrap();
+        assert_eq!(
+            world.find_path(point![0.0, 0.0], point![10.0, 0.0], 0.5),
+            None
+        );
+        assert_eq!(
+            world.find_path(point![10.0, 0.0], point![0.0, 0.0], 0.5),
+            None
+        );
+    }
+
+    #[test]
+    fn new_walls_mean_new_paths() {
+        let mut world = GameWorld::load_level(BOX).unwrap();
+        let (from, to) = (point![-3.0, 0.0], point![3.0, 0.0]);
+        assert_eq!(world.find_path(from, to, 0.5), Some(vec![to]));
+        let revision = world.navigation.revision();
+        let mut level_geometry = world.get_level_geometry().clone();
+        level_geometry
+            .segments
+            .push(LineSegment::new(point![0.0, -5.0], point![0.0, 5.0]));
+        world.set_level_geometry(level_geometry);
+        assert_ne!(world.navigation.revision(), revision);
+        let path = world.find_path(from, to, 0.5).unwrap();
+        assert!(path.len() >= 2, "{path:?}");
+        assert_clear(world.get_level_geometry(), path[0], &path[1..], 0.5);
+    }
+}
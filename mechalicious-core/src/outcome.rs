@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutcomeReason {
+    /// Everybody else is dead, and can't come back.
+    LastTeamStanding,
+    /// Everybody is dead, and nobody can come back.
+    Wipeout,
+    /// PVE: the defenders lost the core.
+    CoreLost,
+    /// Overtime ran out. Whoever had the most kills won, if anybody did.
+    TimeUp,
+    /// Whatever a custom `GameMode` says.
+    Custom(&'static str),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TeamStats {
+    pub kills: u32,
+    pub deaths: u32,
+    pub resources_earned: f32,
+    pub resources_spent: f32,
+    pub final_balance: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatchStats {
+    pub ticks: u64,
+    pub duration: f32, // seconds
+    pub teams: BTreeMap<TeamId, TeamStats>,
+}
+
+impl MatchStats {
+    // Kills and deaths, as they happen. Only things on a team count: goats
+    // and neutral bosses aren't anybody's score.
+    fn count_deaths(&mut self, events: &[GameEvent]) {
+        for event in events.iter() {
+            let GameEvent::Death {
+                team, killer_team, ..
+            } = event
+            else {
+                continue;
+            };
+            let Some(team_id) = team.0 else {
+                continue;
+            };
+            self.teams.entry(team_id).or_default().deaths += 1;
+            if let (Some(killer_team_id), true) = (killer_team.0, killer_team != team) {
+                self.teams.entry(killer_team_id).or_default().kills += 1;
+            }
+        }
+    }
+    // The money side of things all comes out of the bank's books.
+    fn count_resources(&mut self, bank: &ResourceBank) {
+        for team_id in bank.teams() {
+            self.teams.entry(team_id).or_default();
+        }
+        for (team_id, team_stats) in self.teams.iter_mut() {
+            team_stats.resources_earned = bank.earned(*team_id);
+            team_stats.resources_spent = bank.spent(*team_id);
+            team_stats.final_balance = bank.balance(*team_id);
+        }
+    }
+}
+
+/// How the match ended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchOutcome {
+    /// `None` if nobody won.
+    pub winner: Option<TeamId>,
+    pub reason: OutcomeReason,
+    pub stats: MatchStats,
+}
+
+/// How the match is going, and, once it's over, how it ended.
+#[derive(Clone, Debug, Default)]
+pub struct MatchResult {
+    pub stats: MatchStats,
+    pub outcome: Option<MatchOutcome>,
+}
+
+/// Keeps score, and checks whether anybody has won or lost yet. Once
+/// somebody has, that's final.
+pub(crate) fn evaluate_outcome(world: &EcsWorld, dt: f32, events: &mut Vec<GameEvent>) {
+    let result = ecs_singleton!(world, mut MatchResult);
+    if result.outcome.is_some() {
+        return;
+    }
+    result.stats.ticks += 1;
+    result.stats.duration += dt;
+    result.stats.count_deaths(events);
+    let mode = ecs_singleton!(world, cur MatchSettings).mode.clone();
+    if let Some((winner, reason)) = mode.decide(world) {
+        decide(world, winner, reason, events);
+    }
+}
+
+/// Ends the match, right now, unless it's already over.
+pub(crate) fn decide(
+    world: &EcsWorld,
+    winner: Option<TeamId>,
+    reason: OutcomeReason,
+    events: &mut Vec<GameEvent>,
+) {
+    let result = ecs_singleton!(world, mut MatchResult);
+    if result.outcome.is_some() {
+        return;
+    }
+    let mut stats = result.stats.clone();
+    stats.count_resources(ecs_singleton!(world, cur ResourceBank));
+    result.outcome = Some(MatchOutcome {
+        winner,
+        reason,
+        stats,
+    });
+    events.push(GameEvent::MatchDecided { winner, reason });
+}
+
+/// Whoever has the most kills, if anybody has more than everybody else.
+pub fn most_kills(world: &EcsWorld) -> Option<TeamId> {
+    let result = ecs_singleton!(world, cur MatchResult);
+    let best = result.stats.teams.values().map(|team| team.kills).max()?;
+    let mut leaders = result
+        .stats
+        .teams
+        .iter()
+        .filter(|(_, team)| team.kills == best);
+    match (leaders.next(), leaders.next()) {
+        (Some((team_id, _)), None) => Some(*team_id),
+        _ => None,
+    }
+}
+
+/// The usual way to win: be the only team that isn't eliminated. If there's
+/// only one team, it can't win this way, but it can still lose.
+pub fn last_team_standing(world: &EcsWorld) -> Option<(Option<TeamId>, OutcomeReason)> {
+    let players = ecs_singleton!(world, cur Players);
+    let teams: BTreeSet<TeamId> = players.iter().map(|(_, info)| info.team_id).collect();
+    let standing: Vec<TeamId> = teams
+        .iter()
+        .copied()
+        .filter(|team_id| !is_eliminated(world, *team_id))
+        .collect();
+    match (teams.len(), standing.len()) {
+        (0, _) => None,
+        (_, 0) => Some((None, OutcomeReason::Wipeout)),
+        (1, _) => None,
+        (_, 1) => Some((Some(standing[0]), OutcomeReason::LastTeamStanding)),
+        _ => None,
+    }
+}
+
+/// True if everybody on `team_id` is dead, and none of them can come back:
+/// the team has no spawn point, or can't pay for anybody, and has no income
+/// that might let it pay later. What counts as a spawn point or income is up
+/// to the `GameMode`.
+pub fn is_eliminated(world: &EcsWorld, team_id: TeamId) -> bool {
+    let players = ecs_singleton!(world, cur Players);
+    let team_players: Vec<(PlayerId, &PlayerInfo)> = players
+        .iter()
+        .filter(|(_, info)| info.team_id == team_id)
+        .collect();
+    if team_players.iter().any(|(_, info)| info.mech.is_some()) {
+        return false;
+    }
+    let mode = &ecs_singleton!(world, cur MatchSettings).mode;
+    if !mode.spawn_points(world).contains_key(&team_id) {
+        return true;
+    }
+    if mode.has_income(world, team_id) {
+        return false;
+    }
+    let balance = ecs_singleton!(world, cur ResourceBank).balance(team_id);
+    let respawn_queue = ecs_singleton!(world, cur RespawnQueue);
+    !team_players.iter().any(|(player, _)| {
+        respawn_queue
+            .get(*player)
+            .map(|pending| pending.cost <= balance)
+            .unwrap_or(false)
+    })
+}
+
+impl GameWorld {
+    /// How the match ended, if it has.
+    pub fn get_outcome(&self) -> Option<MatchOutcome> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_singleton!(world, cur MatchResult).outcome.clone()
+    }
+    /// The score so far. Once the match is over, the final score.
+    pub fn get_match_stats(&self) -> MatchStats {
+        let world: &EcsWorld = &self.ecs_world;
+        let result = ecs_singleton!(world, cur MatchResult);
+        match &result.outcome {
+            Some(outcome) => outcome.stats.clone(),
+            None => {
+                let mut stats = result.stats.clone();
+                stats.count_resources(ecs_singleton!(world, cur ResourceBank));
+                stats
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goat_farming_doesnt_win_on_time() {
+        let lvl = "loop -6 -6 6 -6 6 6 -6 6\nspawn -5 -5 0\nspawn 5 5 1\ngoat 3 0 2\n";
+        let mut world = GameWorld::load_level(lvl).unwrap();
+        world.set_match_settings(MatchSettings {
+            time_limit: Some(1.0),
+            overtime: 1.0,
+            ..Default::default()
+        });
+        let player = world.add_player(TeamId(0));
+        world.add_player(TeamId(1));
+        world.tick(&[]);
+        // team 0 shoots every goat
+        let mech = world.get_player_mech(player).unwrap();
+        for goat in world.get_goats() {
+            world.with_ecs_world(|world| {
+                let mut health = ecs_get!(world, goat.entity_id, mut Health).unwrap();
+                health.current = 0.0;
+                health.last_attacker = Some(mech);
+            });
+        }
+        world.tick(&[]);
+        assert!(world.get_goats().is_empty());
+        let stats = world.get_match_stats();
+        assert_eq!(stats.teams[&TeamId(0)].kills, 0);
+        assert_eq!(stats.teams[&TeamId(0)].deaths, 0);
+        for _ in 0..200 {
+            world.tick(&[]);
+        }
+        let outcome = world.get_outcome().unwrap();
+        assert_eq!(outcome.reason, OutcomeReason::TimeUp);
+        assert_eq!(outcome.winner, None);
+    }
+}
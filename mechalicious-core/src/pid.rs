@@ -23,11 +23,13 @@ impl PidController {
             integral: 0.0,
         }
     }
-    pub fn get_control_output(&mut self, target_delta: f32, current_velocity: f32) -> f32 {
+    // `current_velocity` is per second, and `dt` is how many seconds have
+    // gone by since last time.
+    pub fn get_control_output(&mut self, target_delta: f32, current_velocity: f32, dt: f32) -> f32 {
         let ret = target_delta * self.proportional_coefficient
-            + (self.integral + target_delta * 0.5) * self.integral_coefficient
+            + (self.integral + target_delta * dt * 0.5) * self.integral_coefficient
             - current_velocity * self.derivative_coefficient;
-        self.integral += target_delta;
+        self.integral += target_delta * dt;
         ret.clamp(-1.0, 1.0)
     }
 }
@@ -38,7 +40,7 @@ impl Default for PidController {
         Self {
             proportional_coefficient: 1.0,
             integral_coefficient: 0.0,
-            derivative_coefficient: 5.0,
+            derivative_coefficient: 5.0 / 60.0,
             integral: 0.0,
         }
     }
@@ -0,0 +1,258 @@
+use super::*;
+
+/// Why somebody couldn't buy something.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PurchaseRejection {
+    /// The thing it was supposed to go on isn't there (or isn't that kind of
+    /// thing).
+    NoSuchSite,
+    /// You have to own the place first.
+    NotOwned,
+    /// The place isn't big enough for that.
+    TierTooLow,
+    /// Something's already guarding it.
+    AlreadyGuarded,
+    InsufficientFunds,
+    /// There's something sitting where it would come out.
+    SpawnBlocked,
+    /// The team already has as many units as it's allowed.
+    UnitCapReached,
+    /// Nobody's ever heard of that kind of unit.
+    UnknownUnitType,
+    /// The match hasn't started yet, or it's over. No shopping.
+    NotInProgress,
+}
+
+/// Stand on it (or, if you're already standing on it, interact with it) to
+/// buy a unit. It belongs to whoever owns `base`.
+#[derive(Clone, Debug)]
+pub struct PurchaseButton {
+    pub unit_type: String,
+    /// Where the units come out.
+    pub factory: Point,
+    /// The spawn point this button is part of.
+    pub base: EntityId,
+    pub radius: f32,
+    // so that it goes off once when you step on it (or hit interact), not
+    // every tick that you stay there
+    occupied: bool,
+    interacting: bool,
+}
+
+impl PurchaseButton {
+    pub fn new(unit_type: String, factory: Point, base: EntityId) -> PurchaseButton {
+        PurchaseButton {
+            unit_type,
+            factory,
+            base,
+            radius: 0.75,
+            occupied: false,
+            interacting: false,
+        }
+    }
+}
+
+impl GameWorld {
+    /// Builds a miniboss for `team_id` at `node`, which they have to own, and
+    /// which has to be a high enough tier and not already guarded. Takes the
+    /// cost out of their bank.
+    pub fn buy_miniboss(
+        &mut self,
+        team_id: TeamId,
+        node: EntityId,
+    ) -> Result<EntityId, PurchaseRejection> {
+        let mut result = Err(PurchaseRejection::NoSuchSite);
+        self.ecs_world = self.ecs_world.buffered_tick(|world| {
+            result = buy_miniboss(world, team_id, node);
+        });
+        result
+    }
+}
+
+fn buy_miniboss(
+    world: &mut EcsWorld,
+    team_id: TeamId,
+    node: EntityId,
+) -> Result<EntityId, PurchaseRejection> {
+    if !ecs_singleton!(world, cur MatchClock)
+        .phase
+        .runs(GameSystem::Economy)
+        || ecs_singleton!(world, cur MatchResult).outcome.is_some()
+    {
+        return Err(PurchaseRejection::NotInProgress);
+    }
+    let match_settings = ecs_singleton!(world, cur MatchSettings).clone();
+    let (position, tier, owner, guardian) = ecs_get!(
+        world,
+        node,
+        cur Placement,
+        cur ResourceNode,
+        cur Team,
+        cur CaptureZone
+    )
+    .map(|(placement, resource_node, team, zone)| {
+        (placement.position, resource_node.tier, *team, zone.guardian)
+    })
+    .ok_or(PurchaseRejection::NoSuchSite)?;
+    if owner != Team::of(team_id) {
+        return Err(PurchaseRejection::NotOwned);
+    }
+    if tier < match_settings.miniboss_min_tier {
+        return Err(PurchaseRejection::TierTooLow);
+    }
+    if guardian
+        .map(|guardian| is_alive(world, guardian))
+        .unwrap_or(false)
+    {
+        return Err(PurchaseRejection::AlreadyGuarded);
+    }
+    if !ecs_singleton!(world, mut ResourceBank).debit(
+        team_id,
+        match_settings.miniboss_cost,
+        TransactionReason::Purchase,
+    ) {
+        return Err(PurchaseRejection::InsufficientFunds);
+    }
+    Ok(spawn_miniboss(world, position, owner, node))
+}
+
+/// Notices friendly mechs stepping onto (or interacting with) buttons, and
+/// buys whatever the button sells, if the team can.
+pub(crate) fn update_buttons(
+    world: &mut EcsWorld,
+    spatial_hash: &SpatialHash,
+    events: &mut Vec<GameEvent>,
+) {
+    let mut pressed = vec![];
+    for (entity_id, placement, button) in ecs_iter!(world, cur Placement, mut PurchaseButton) {
+        let Some(owner) = ecs_get!(world, button.base, cur Team).copied() else {
+            continue;
+        };
+        let mut occupied = false;
+        let mut interacting = false;
+        for entry in spatial_hash.query_radius(placement.position, button.radius) {
+            let Some((_pilot, team, health, controls)) = ecs_get!(
+                world,
+                entry.entity_id,
+                cur Pilot,
+                cur Team,
+                cur Health,
+                cur ShipControls
+            ) else {
+                continue;
+            };
+            if !team.is_friendly_to(&owner) || health.is_dead() {
+                continue;
+            }
+            occupied = true;
+            interacting |= controls.interact;
+        }
+        let triggered = (occupied && !button.occupied) || (interacting && !button.interacting);
+        button.occupied = occupied;
+        button.interacting = interacting;
+        if let (true, Some(team_id)) = (triggered, owner.0) {
+            pressed.push((entity_id, team_id, button.unit_type.clone(), button.factory));
+        }
+    }
+    let mut spawned = vec![];
+    for (button, team_id, unit_type, factory) in pressed {
+        match buy_unit(
+            world,
+            spatial_hash,
+            &mut spawned,
+            team_id,
+            &unit_type,
+            factory,
+        ) {
+            Ok(unit) => events.push(GameEvent::UnitPurchased {
+                button,
+                unit,
+                team_id,
+            }),
+            Err(reason) => events.push(GameEvent::PurchaseRejected {
+                button,
+                team_id,
+                reason,
+            }),
+        }
+    }
+}
+
+// `spawned` is everything bought earlier this tick, which the spatial hash
+// doesn't know about yet: where it is, and how big.
+fn buy_unit(
+    world: &mut EcsWorld,
+    spatial_hash: &SpatialHash,
+    spawned: &mut Vec<(Point, f32)>,
+    team_id: TeamId,
+    unit_type: &str,
+    factory: Point,
+) -> Result<EntityId, PurchaseRejection> {
+    let unit_type = ecs_singleton!(world, cur UnitTypes)
+        .get(unit_type)
+        .cloned()
+        .ok_or(PurchaseRejection::UnknownUnitType)?;
+    if count_units(world, team_id) >= ecs_singleton!(world, cur MatchSettings).unit_cap {
+        return Err(PurchaseRejection::UnitCapReached);
+    }
+    // Only solid things get in the way.
+    if spatial_hash
+        .query_radius(factory, unit_type.size)
+        .into_iter()
+        .any(|entry| entry.radius > 0.0)
+        || spawned
+            .iter()
+            .any(|(position, radius)| (position - factory).magnitude() <= radius + unit_type.size)
+    {
+        return Err(PurchaseRejection::SpawnBlocked);
+    }
+    if !ecs_singleton!(world, mut ResourceBank).debit(
+        team_id,
+        unit_type.cost,
+        TransactionReason::Purchase,
+    ) {
+        return Err(PurchaseRejection::InsufficientFunds);
+    }
+    spawned.push((factory, unit_type.size));
+    Ok(spawn_unit(world, &unit_type, factory, Team::of(team_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_factory_one_unit_per_tick() {
+        // two buttons, right next to each other, with the same factory
+        let mut world =
+            GameWorld::load_level("spawn 0 0 0\nbutton grunt 3 0 -3 0\nbutton grunt 3 0.5 -3 0")
+                .unwrap();
+        let player = world.add_player(TeamId(0));
+        world.tick(&[]);
+        let mech = world.get_player_mech(player).unwrap();
+        world.with_ecs_world(|world| {
+            ecs_get!(world, mech, mut Placement).unwrap().position = point![3.0, 0.25];
+        });
+        world.tick(&[]);
+        let purchased = world
+            .get_events()
+            .iter()
+            .filter(|event| matches!(event, GameEvent::UnitPurchased { .. }))
+            .count();
+        let blocked = world
+            .get_events()
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    GameEvent::PurchaseRejected {
+                        reason: PurchaseRejection::SpawnBlocked,
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!((purchased, blocked), (1, 1));
+        assert_eq!(world.count_units(TeamId(0)), 1);
+    }
+}
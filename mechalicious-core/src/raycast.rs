@@ -0,0 +1,87 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RaycastTarget {
+    /// An index into `LevelGeometry::segments`.
+    Wall(usize),
+    Entity(EntityId),
+}
+
+#[derive(Clone, Debug)]
+pub struct RaycastHit {
+    pub target: RaycastTarget,
+    pub point: Point,
+    /// Points away from whatever got hit, back toward the ray.
+    pub normal: Vector,
+    pub distance: f32,
+}
+
+/// Shoots a ray, and finds the first wall or collider it hits. Only entities
+/// that `filter` likes can be hit (so, for instance, you can leave out
+/// whoever's doing the shooting). `direction` doesn't need to be normalized.
+pub fn raycast(
+    level_geometry: &LevelGeometry,
+    spatial_hash: &SpatialHash,
+    origin: Point,
+    direction: Vector,
+    max_distance: f32,
+    mut filter: impl FnMut(EntityId) -> bool,
+) -> Option<RaycastHit> {
+    let direction = direction.try_normalize(0.0)?;
+    let wall_hit =
+        level_geometry
+            .raycast(origin, direction, max_distance)
+            .map(|(distance, index, normal)| RaycastHit {
+                target: RaycastTarget::Wall(index),
+                point: origin + direction * distance,
+                normal,
+                distance,
+            });
+    // no point looking for entities on the far side of the wall
+    let max_distance = wall_hit
+        .as_ref()
+        .map(|hit| hit.distance)
+        .unwrap_or(max_distance);
+    let entity_hit = spatial_hash
+        .raycast(origin, direction, max_distance, |entry| {
+            filter(entry.entity_id)
+        })
+        .map(|(entry, distance)| {
+            let point = origin + direction * distance;
+            RaycastHit {
+                target: RaycastTarget::Entity(entry.entity_id),
+                point,
+                normal: (point - entry.position)
+                    .try_normalize(0.0)
+                    .unwrap_or(-direction),
+                distance,
+            }
+        });
+    entity_hit.or(wall_hit)
+}
+
+impl GameWorld {
+    /// See `raycast::raycast`. Entities are where they were as of the last
+    /// tick.
+    pub fn raycast(
+        &self,
+        origin: Point,
+        direction: Vector,
+        max_distance: f32,
+        filter: impl FnMut(EntityId) -> bool,
+    ) -> Option<RaycastHit> {
+        raycast(
+            &self.level_geometry,
+            &self.spatial_hash,
+            origin,
+            direction,
+            max_distance,
+            filter,
+        )
+    }
+    /// True if there are no walls between `a` and `b`. Doesn't care about
+    /// entities; things can see past each other.
+    pub fn line_of_sight(&self, a: Point, b: Point) -> bool {
+        self.level_geometry.line_of_sight(a, b)
+    }
+}
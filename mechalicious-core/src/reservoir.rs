@@ -0,0 +1,106 @@
+use super::*;
+
+/// Empties every owned core into its owner's bank, `core_drain_rate` per
+/// second, until there's nothing left.
+pub fn drain_cores(world: &EcsWorld, dt: f32) {
+    let drain_rate = ecs_singleton!(world, cur MatchSettings).core_drain_rate;
+    let bank = ecs_singleton!(world, mut ResourceBank);
+    for (_entity_id, core, team) in ecs_iter!(world, mut Core, cur Team) {
+        let Some(team_id) = team.0 else {
+            continue;
+        };
+        let amount = (drain_rate * dt).min(core.resources);
+        if amount <= 0.0 {
+            continue;
+        }
+        core.resources -= amount;
+        bank.credit(team_id, amount, TransactionReason::CoreDrain);
+    }
+}
+
+/// Moves a trickle out of the core and into the bank of whoever owns each
+/// resource node. Once the core's empty, that's it.
+pub fn siphon_nodes(world: &EcsWorld, dt: f32) {
+    let income_rate = ecs_singleton!(world, cur MatchSettings).node_income_rate;
+    let owned_nodes: Vec<(TeamId, u32)> = ecs_iter!(world, cur ResourceNode, cur Team)
+        .filter_map(|(_entity_id, node, team)| Some((team.0?, node.tier)))
+        .collect();
+    if owned_nodes.is_empty() {
+        return;
+    }
+    let bank = ecs_singleton!(world, mut ResourceBank);
+    for (_entity_id, core) in ecs_iter!(world, mut Core) {
+        for (team_id, tier) in owned_nodes.iter() {
+            let amount = (income_rate * *tier as f32 * dt).min(core.resources);
+            if amount <= 0.0 {
+                continue;
+            }
+            core.resources -= amount;
+            bank.credit(*team_id, amount, TransactionReason::NodeIncome);
+        }
+    }
+}
+
+/// True if `team_id` owns something that's still paying out: the core, or a
+/// node, while there's anything left in the core.
+pub fn has_income(world: &EcsWorld, team_id: TeamId) -> bool {
+    let core_resources: f32 = ecs_iter!(world, cur Core)
+        .map(|(_entity_id, core)| core.resources)
+        .sum();
+    let owns = |team: &Team| *team == Team::of(team_id);
+    core_resources > 0.0
+        && (ecs_iter!(world, cur ResourceNode, cur Team).any(|(_, _, team)| owns(team))
+            || ecs_iter!(world, cur Core, cur Team).any(|(_, _, team)| owns(team)))
+}
+
+/// What a resource node looks like from the outside.
+#[derive(Clone, Debug)]
+pub struct ResourceNodeInfo {
+    pub entity_id: EntityId,
+    pub position: Point,
+    pub tier: u32,
+    pub owner: Team,
+    /// Who's taking it, and how far along they are (0.0 to 1.0).
+    pub capturing_team: Option<TeamId>,
+    pub capture_progress: f32,
+}
+
+impl GameWorld {
+    /// Every resource node, who owns it, and who's trying to take it.
+    pub fn get_resource_nodes(&self) -> Vec<ResourceNodeInfo> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_iter!(
+            world,
+            cur Placement,
+            cur ResourceNode,
+            cur Team,
+            cur CaptureZone
+        )
+        .map(
+            |(entity_id, placement, node, team, zone)| ResourceNodeInfo {
+                entity_id,
+                position: placement.position,
+                tier: node.tier,
+                owner: *team,
+                capturing_team: zone.capturing_team,
+                capture_progress: zone.progress,
+            },
+        )
+        .collect()
+    }
+    /// The core, if the level has one.
+    pub fn get_core(&self) -> Option<EntityId> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_iter!(world, cur Core)
+            .map(|(entity_id, _core)| entity_id)
+            .next()
+    }
+    /// How much is still in the core. Zero if there isn't one.
+    pub fn get_core_resources(&self) -> f32 {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_iter!(world, cur Core)
+            .map(|(_entity_id, core)| core.resources)
+            .next()
+            .unwrap_or(0.0)
+    }
+}
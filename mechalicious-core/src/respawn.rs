@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use super::*;
+
+#[derive(Clone, Debug)]
+pub struct PlayerInfo {
+    pub team_id: TeamId,
+    /// The mech they're flying right now, if they're alive.
+    pub mech: Option<EntityId>,
+}
+
+/// Everybody who's playing.
+#[derive(Clone, Debug, Default)]
+pub struct Players {
+    next_player_id: u32,
+    players: BTreeMap<PlayerId, PlayerInfo>,
+}
+
+impl Players {
+    pub fn get(&self, player: PlayerId) -> Option<&PlayerInfo> {
+        self.players.get(&player)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (PlayerId, &PlayerInfo)> {
+        self.players.iter().map(|(player, info)| (*player, info))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RespawnRejection {
+    /// Your team doesn't own a spawn point.
+    NoSpawnPoint,
+    /// Your team can't afford to respawn you.
+    InsufficientResources,
+}
+
+#[derive(Clone, Debug)]
+pub struct PendingRespawn {
+    pub player: PlayerId,
+    pub time_left: f32, // seconds
+    pub cost: f32,
+    /// Why we couldn't respawn them the last time we tried. They stay in
+    /// the queue, and we keep trying.
+    pub rejection: Option<RespawnRejection>,
+}
+
+/// Everybody who's waiting to get back in, in the order they died.
+#[derive(Clone, Debug, Default)]
+pub struct RespawnQueue {
+    pub pending: Vec<PendingRespawn>,
+}
+
+impl RespawnQueue {
+    pub fn get(&self, player: PlayerId) -> Option<&PendingRespawn> {
+        self.pending.iter().find(|x| x.player == player)
+    }
+}
+
+pub(crate) fn spawn_mech(
+    world: &mut EcsWorld,
+    position: Point,
+    team_id: TeamId,
+    player: PlayerId,
+) -> EntityId {
+    ecs_spawn!(
+        world,
+        Placement {
+            position,
+            angle: 0.0,
+            scale: 0.3,
+        },
+        Physics {
+            mass: 1.0,
+            moment: 1.0,
+            force: vector![0.0, 0.0],
+            torque: 0.0,
+            velocity: vector![0.0, 0.0],
+            angular_velocity: 0.0,
+        },
+        Collider {
+            radius: 1.0,
+            restitution: 0.3,
+            friction: 0.2,
+        },
+        ShipControls::default(),
+        ShipControlCharacteristics::default(),
+        Weapon {
+            fire_rate: 8.0,
+            muzzle_offset: vector![1.1, 0.0],
+            projectile_speed: 20.0,
+            spread: 0.05,
+            projectile_lifetime: 1.0,
+            damage: 10.0,
+            cooldown: 0.0,
+        },
+        Health::new(100.0, DeathBehavior::Wreck),
+        Team::of(team_id),
+        Pilot { player },
+        Capturer {
+            kind: CapturerKind::Mech,
+        },
+        Visible {
+            model_path: "mechalicious.v2d",
+        },
+    )
+}
+
+/// The spawn point each team comes back at, out of the ones it owns. The
+/// first one is as good as any.
+pub fn owned_spawn_points(world: &EcsWorld) -> BTreeMap<TeamId, Point> {
+    let mut spawn_points: BTreeMap<TeamId, Point> = BTreeMap::new();
+    for (_entity_id, placement, _spawn_point, team) in
+        ecs_iter!(world, cur Placement, cur SpawnPoint, cur Team)
+    {
+        if let Some(team_id) = team.0 {
+            spawn_points.entry(team_id).or_insert(placement.position);
+        }
+    }
+    spawn_points
+}
+
+/// Puts players whose mechs died this tick in the respawn queue. This
+/// happens whenever mechs can die, even if nobody's coming back right now,
+/// so nobody's left flying a mech that isn't there anymore.
+pub(crate) fn queue_respawns(world: &EcsWorld, events: &[GameEvent]) {
+    let match_settings = ecs_singleton!(world, cur MatchSettings);
+    let mut newly_dead = vec![];
+    {
+        let players = ecs_singleton!(world, mut Players);
+        for event in events.iter() {
+            let GameEvent::Death { entity_id, .. } = event else {
+                continue;
+            };
+            for (player, info) in players.players.iter_mut() {
+                if info.mech == Some(*entity_id) {
+                    info.mech = None;
+                    newly_dead.push(*player);
+                }
+            }
+        }
+    }
+    let respawn_queue = ecs_singleton!(world, mut RespawnQueue);
+    for player in newly_dead {
+        respawn_queue.pending.push(PendingRespawn {
+            player,
+            time_left: match_settings.respawn_delay,
+            cost: match_settings.respawn_cost,
+            rejection: None,
+        });
+    }
+}
+
+/// Brings back everyone whose wait is over, if their team can pay for it.
+pub(crate) fn update_respawns(world: &mut EcsWorld, dt: f32, events: &mut Vec<GameEvent>) {
+    let match_settings = ecs_singleton!(world, cur MatchSettings).clone();
+    for pending in ecs_singleton!(world, mut RespawnQueue).pending.iter_mut() {
+        pending.time_left = (pending.time_left - dt).max(0.0);
+    }
+    let spawn_points = match_settings.mode.spawn_points(world);
+    // Who's ready to come back?
+    let mut respawns = vec![];
+    {
+        let pending = std::mem::take(&mut ecs_singleton!(world, mut RespawnQueue).pending);
+        let players = ecs_singleton!(world, cur Players);
+        let bank = ecs_singleton!(world, mut ResourceBank);
+        let mut still_pending = vec![];
+        for mut respawn in pending {
+            let Some(info) = players.get(respawn.player) else {
+                // they left
+                continue;
+            };
+            if respawn.time_left > 0.0 {
+                still_pending.push(respawn);
+                continue;
+            }
+            let rejection = match spawn_points.get(&info.team_id) {
+                None => Some(RespawnRejection::NoSpawnPoint),
+                Some(_) if !bank.debit(info.team_id, respawn.cost, TransactionReason::Respawn) => {
+                    Some(RespawnRejection::InsufficientResources)
+                }
+                Some(position) => {
+                    respawns.push((respawn.player, info.team_id, *position));
+                    None
+                }
+            };
+            if let Some(rejection) = rejection {
+                // only say so when the reason changes, not every tick
+                if respawn.rejection != Some(rejection) {
+                    events.push(GameEvent::RespawnRejected {
+                        player: respawn.player,
+                        reason: rejection,
+                    });
+                }
+                respawn.rejection = Some(rejection);
+                still_pending.push(respawn);
+            }
+        }
+        ecs_singleton!(world, mut RespawnQueue).pending = still_pending;
+    }
+    for (player, team_id, position) in respawns {
+        let entity_id = spawn_mech(world, position, team_id, player);
+        if let Some(info) = ecs_singleton!(world, mut Players).players.get_mut(&player) {
+            info.mech = Some(entity_id);
+        }
+        events.push(GameEvent::Respawned { player, entity_id });
+    }
+}
+
+impl GameWorld {
+    /// Somebody new wants to play on `team_id`. They'll spawn (for free) at
+    /// the next tick, if their team owns a spawn point.
+    pub fn add_player(&mut self, team_id: TeamId) -> PlayerId {
+        let mut new_player = None;
+        self.ecs_world = self.ecs_world.buffered_tick(|world| {
+            let starting_resources = ecs_singleton!(world, cur MatchSettings).starting_resources;
+            let bank = ecs_singleton!(world, mut ResourceBank);
+            if !bank.has_account(team_id) {
+                bank.credit(
+                    team_id,
+                    starting_resources,
+                    TransactionReason::StartingResources,
+                );
+            }
+            let players = ecs_singleton!(world, mut Players);
+            let player = PlayerId(players.next_player_id);
+            players.next_player_id += 1;
+            players.players.insert(
+                player,
+                PlayerInfo {
+                    team_id,
+                    mech: None,
+                },
+            );
+            ecs_singleton!(world, mut RespawnQueue)
+                .pending
+                .push(PendingRespawn {
+                    player,
+                    time_left: 0.0,
+                    cost: 0.0,
+                    rejection: None,
+                });
+            new_player = Some(player);
+        });
+        new_player.unwrap()
+    }
+    /// The mech that `player` is flying, if they're alive.
+    pub fn get_player_mech(&self, player: PlayerId) -> Option<EntityId> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_singleton!(world, cur Players)
+            .get(player)
+            .and_then(|info| info.mech)
+    }
+    /// If `player` is waiting to respawn, how that's going.
+    pub fn get_pending_respawn(&self, player: PlayerId) -> Option<PendingRespawn> {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_singleton!(world, cur RespawnQueue).get(player).cloned()
+    }
+}
@@ -0,0 +1,118 @@
+use super::*;
+
+/// What `GameWorld::load_level` seeds the world with, if you don't pick a
+/// seed yourself.
+pub const DEFAULT_SEED: u64 = 0x6d65_6368_616c_6963; // "mechalic"
+
+/// The one and only source of randomness in the simulation. It lives in the
+/// world like everything else, so the same seed and the same inputs always
+/// play out the same way, and rolling the world back rolls this back too.
+///
+/// It's SplitMix64: one `u64` of state, which is all there is to save and
+/// restore (see `state` and `from_state`). Anything in `rand` that takes an
+/// `Rng` will take this.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn from_state(state: u64) -> GameRng {
+        GameRng { state }
+    }
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for GameRng {
+    type Seed = [u8; 8];
+    fn from_seed(seed: Self::Seed) -> GameRng {
+        GameRng::from_state(u64::from_le_bytes(seed))
+    }
+    // The default would run the seed through a different generator first.
+    // There's no need; SplitMix64 is happy with any seed.
+    fn seed_from_u64(seed: u64) -> GameRng {
+        GameRng::from_state(seed)
+    }
+}
+
+impl GameWorld {
+    /// Where the world's RNG is at right now. Save this, and hand it to
+    /// `set_rng_state` later, to pick up where it left off.
+    pub fn get_rng_state(&self) -> u64 {
+        let world: &EcsWorld = &self.ecs_world;
+        ecs_singleton!(world, cur GameRng).state()
+    }
+    pub fn set_rng_state(&mut self, state: u64) {
+        self.ecs_world = self.ecs_world.buffered_tick(|world| {
+            *ecs_singleton!(world, mut GameRng) = GameRng::from_state(state);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays the test level for a bit, with one player doing the same things
+    // every time, and says where everything ended up.
+    fn play(seed: u64) -> (u64, Vec<(EntityId, Point)>) {
+        let mut world = GameWorld::load_level_seeded(
+            include_str!("../../mechalicious-client/data/test.level"),
+            seed,
+        )
+        .unwrap();
+        let player = world.add_player(TeamId(0));
+        for tick in 0..300 {
+            let angle = tick as f32 * 0.05;
+            let controls = ShipControls {
+                movement: vector![angle.cos(), angle.sin()],
+                aim: vector![angle.sin(), angle.cos()],
+                fire: tick % 20 < 10,
+                ..Default::default()
+            };
+            match world.get_player_mech(player) {
+                Some(mech) => world.tick(&[(mech, &controls)]),
+                None => world.tick(&[]),
+            }
+        }
+        let world_ref = world.get_ecs_world();
+        let positions = ecs_iter!(world_ref, cur Placement)
+            .map(|(entity_id, placement)| (entity_id, placement.position))
+            .collect();
+        (world.get_rng_state(), positions)
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let (state, positions) = play(1234);
+        assert_eq!(play(1234), (state, positions.clone()));
+        let (other_state, other_positions) = play(5678);
+        assert_ne!(other_state, state);
+        assert_ne!(other_positions, positions);
+    }
+}
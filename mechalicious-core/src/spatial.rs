@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use super::*;
+
+// How wide one cell of the hash is, in world units. Should be a bit bigger
+// than most of the things we put in it.
+pub const DEFAULT_CELL_SIZE: f32 = 2.0;
+
+type CellCoords = (i32, i32);
+
+/// Something that was put in the hash.
+#[derive(Clone, Debug)]
+pub struct SpatialEntry {
+    pub entity_id: EntityId,
+    pub position: Point,
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CellRange {
+    min: CellCoords,
+    max: CellCoords,
+}
+
+/// A uniform grid of buckets, so that "what's near here?" doesn't have to look
+/// at every single entity in the world. Things with a radius go in every cell
+/// they overlap.
+#[derive(Clone, Debug)]
+pub struct SpatialHash {
+    cell_size: f32,
+    entries: Vec<SpatialEntry>,
+    ranges: Vec<CellRange>,
+    cells: HashMap<CellCoords, Vec<usize>>,
+    // the range of cells that have anything in them at all
+    occupied: Option<CellRange>,
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        SpatialHash::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> SpatialHash {
+        assert!(cell_size > 0.0);
+        SpatialHash {
+            cell_size,
+            entries: vec![],
+            ranges: vec![],
+            cells: HashMap::new(),
+            occupied: None,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.ranges.clear();
+        // keep the buckets around, so we don't reallocate them every tick
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+        self.occupied = None;
+    }
+    pub fn insert(&mut self, entity_id: EntityId, position: Point, radius: f32) {
+        let range = self.cell_range(
+            position - vector![radius, radius],
+            position + vector![radius, radius],
+        );
+        let index = self.entries.len();
+        self.entries.push(SpatialEntry {
+            entity_id,
+            position,
+            radius,
+        });
+        self.ranges.push(range);
+        for y in range.min.1..=range.max.1 {
+            for x in range.min.0..=range.max.0 {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+        self.occupied = Some(match self.occupied {
+            None => range,
+            Some(occupied) => CellRange {
+                min: (
+                    occupied.min.0.min(range.min.0),
+                    occupied.min.1.min(range.min.1),
+                ),
+                max: (
+                    occupied.max.0.max(range.max.0),
+                    occupied.max.1.max(range.max.1),
+                ),
+            },
+        });
+    }
+    /// Throws out everything and puts every entity with a `Placement` back
+    /// in, using its `Collider` (if any) for its size.
+    pub fn rebuild(&mut self, world: &EcsWorld) {
+        self.clear();
+        for (entity_id, placement, collider) in
+            ecs_iter!(world, cur Placement, cur Option<Collider>)
+        {
+            let radius = collider
+                .map(|collider| collider.scaled_radius(placement))
+                .unwrap_or(0.0);
+            self.insert(entity_id, placement.position, radius);
+        }
+    }
+    fn cell_coords(&self, point: Point) -> CellCoords {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+    fn cell_range(&self, min: Point, max: Point) -> CellRange {
+        CellRange {
+            min: self.cell_coords(min),
+            max: self.cell_coords(max),
+        }
+    }
+    // Calls `handler` once for every entry whose cells overlap `range`.
+    fn for_each_in_range<'a>(
+        &'a self,
+        range: CellRange,
+        mut handler: impl FnMut(&'a SpatialEntry),
+    ) {
+        for y in range.min.1..=range.max.1 {
+            for x in range.min.0..=range.max.0 {
+                let Some(bucket) = self.cells.get(&(x, y)) else {
+                    continue;
+                };
+                for &index in bucket.iter() {
+                    // Big things are in more than one cell. Only report them
+                    // from the first cell where they and the range overlap.
+                    let entry_range = &self.ranges[index];
+                    if x != entry_range.min.0.max(range.min.0)
+                        || y != entry_range.min.1.max(range.min.1)
+                    {
+                        continue;
+                    }
+                    handler(&self.entries[index]);
+                }
+            }
+        }
+    }
+    /// Everything whose circle overlaps the box from `min` to `max`.
+    pub fn query_aabb(&self, min: Point, max: Point) -> Vec<&SpatialEntry> {
+        let mut ret = vec![];
+        self.for_each_in_range(self.cell_range(min, max), |entry| {
+            let closest = entry.position.coords.sup(&min.coords).inf(&max.coords);
+            if (entry.position.coords - closest).magnitude_squared() <= entry.radius * entry.radius
+            {
+                ret.push(entry);
+            }
+        });
+        ret
+    }
+    /// Everything whose circle overlaps the circle at `center`.
+    pub fn query_radius(&self, center: Point, radius: f32) -> Vec<&SpatialEntry> {
+        let mut ret = vec![];
+        let range = self.cell_range(
+            center - vector![radius, radius],
+            center + vector![radius, radius],
+        );
+        self.for_each_in_range(range, |entry| {
+            let reach = radius + entry.radius;
+            if (entry.position - center).magnitude_squared() <= reach * reach {
+                ret.push(entry);
+            }
+        });
+        ret
+    }
+    /// The entry whose center is closest to `point`, no further away than
+    /// `max_distance`, that `filter` likes.
+    pub fn nearest(
+        &self,
+        point: Point,
+        max_distance: f32,
+        mut filter: impl FnMut(&SpatialEntry) -> bool,
+    ) -> Option<&SpatialEntry> {
+        let occupied = self.occupied?;
+        let center = self.cell_coords(point);
+        // we never need to look further out than this many rings
+        let farthest_ring = [
+            center.0 - occupied.min.0,
+            occupied.max.0 - center.0,
+            center.1 - occupied.min.1,
+            occupied.max.1 - center.1,
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+        .max(0);
+        let farthest_ring = if max_distance.is_finite() {
+            farthest_ring.min((max_distance / self.cell_size).ceil() as i32 + 1)
+        } else {
+            farthest_ring
+        };
+        let mut best: Option<(f32, &SpatialEntry)> = None;
+        for ring in 0..=farthest_ring {
+            // Everything in ring N is at least (N - 1) cells away. Once we've
+            // got something closer than that, we're done.
+            if let Some((best_distance_squared, _)) = best {
+                let ring_distance = (ring - 1).max(0) as f32 * self.cell_size;
+                if best_distance_squared <= ring_distance * ring_distance {
+                    break;
+                }
+            }
+            for y in center.1 - ring..=center.1 + ring {
+                let on_edge = y == center.1 - ring || y == center.1 + ring;
+                let step = if on_edge { 1 } else { (ring * 2).max(1) };
+                let mut x = center.0 - ring;
+                while x <= center.0 + ring {
+                    for &index in self.cells.get(&(x, y)).into_iter().flatten() {
+                        let entry = &self.entries[index];
+                        let distance_squared = (entry.position - point).magnitude_squared();
+                        if distance_squared > max_distance * max_distance {
+                            continue;
+                        }
+                        // ties go to the lower entity ID, so the answer
+                        // doesn't depend on what order things went in
+                        let better = match best {
+                            None => true,
+                            Some((best_distance_squared, best_entry)) => {
+                                (distance_squared, entry.entity_id)
+                                    < (best_distance_squared, best_entry.entity_id)
+                            }
+                        };
+                        if better && filter(entry) {
+                            best = Some((distance_squared, entry));
+                        }
+                    }
+                    x += step;
+                }
+            }
+        }
+        best.map(|(_, entry)| entry)
+    }
+    /// The first entry whose circle the ray hits, that `filter` likes, and
+    /// how far along the ray it was. `direction` must be a unit vector.
+    pub fn raycast(
+        &self,
+        origin: Point,
+        direction: Vector,
+        max_distance: f32,
+        mut filter: impl FnMut(&SpatialEntry) -> bool,
+    ) -> Option<(&SpatialEntry, f32)> {
+        let occupied = self.occupied?;
+        // No point walking off past the last thing in the hash.
+        let far_corner = point![
+            if direction.x >= 0.0 {
+                (occupied.max.0 + 1) as f32
+            } else {
+                occupied.min.0 as f32
+            },
+            if direction.y >= 0.0 {
+                (occupied.max.1 + 1) as f32
+            } else {
+                occupied.min.1 as f32
+            }
+        ] * self.cell_size;
+        let max_distance = max_distance.min((far_corner - origin).abs().sum());
+        let mut cell = self.cell_coords(origin);
+        // Walk the grid one cell at a time, along the ray. (Amanatides & Woo)
+        let step = (
+            if direction.x >= 0.0 { 1 } else { -1 },
+            if direction.y >= 0.0 { 1 } else { -1 },
+        );
+        let boundary_distance = |cell: i32, step: i32, origin: f32, direction: f32| {
+            if direction == 0.0 {
+                f32::INFINITY
+            } else {
+                let boundary = (cell + step.max(0)) as f32 * self.cell_size;
+                (boundary - origin) / direction
+            }
+        };
+        let mut next_x = boundary_distance(cell.0, step.0, origin.x, direction.x);
+        let mut next_y = boundary_distance(cell.1, step.1, origin.y, direction.y);
+        let delta_x = (self.cell_size / direction.x).abs();
+        let delta_y = (self.cell_size / direction.y).abs();
+        let mut entered_at = 0.0;
+        let mut best: Option<(&SpatialEntry, f32)> = None;
+        while entered_at <= max_distance {
+            if let Some((_, best_distance)) = best {
+                if best_distance <= entered_at {
+                    break;
+                }
+            }
+            for &index in self.cells.get(&cell).into_iter().flatten() {
+                let entry = &self.entries[index];
+                let Some(distance) = ray_circle(origin, direction, entry.position, entry.radius)
+                else {
+                    continue;
+                };
+                if distance > max_distance
+                    || best
+                        .map(|(best_entry, best_distance)| {
+                            (distance, entry.entity_id) >= (best_distance, best_entry.entity_id)
+                        })
+                        .unwrap_or(false)
+                    || !filter(entry)
+                {
+                    continue;
+                }
+                best = Some((entry, distance));
+            }
+            if next_x < next_y {
+                entered_at = next_x;
+                next_x += delta_x;
+                cell.0 += step.0;
+            } else {
+                entered_at = next_y;
+                next_y += delta_y;
+                cell.1 += step.1;
+            }
+        }
+        best
+    }
+}
+
+/// How far along a ray it first touches a circle. Zero if it starts inside.
+fn ray_circle(origin: Point, direction: Vector, center: Point, radius: f32) -> Option<f32> {
+    if radius <= 0.0 {
+        return None;
+    }
+    let offset = origin - center;
+    let b = offset.dot(&direction);
+    let c = offset.magnitude_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    if b > 0.0 {
+        // pointing away
+        return None;
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    Some(-b - discriminant.sqrt())
+}
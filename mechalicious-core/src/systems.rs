@@ -3,44 +3,163 @@ use super::*;
 impl GameWorld {
     pub fn tick(&mut self, inputs: &[(EntityId, &ShipControls)]) {
         self.prev_ecs_world = self.ecs_world.clone();
+        let mut events = vec![];
+        let mut transactions = vec![];
         self.ecs_world = self.ecs_world.buffered_tick(|world| {
             // this is where our Systems go
+            ecs_singleton!(world, mut ResourceBank).advance_tick();
+            let world_physics = ecs_singleton!(world, cur WorldPhysics).clone();
+            let dt = world_physics.dt;
+            // Match Phase System
+            lifecycle::update_phase(world, dt, &mut events);
+            let phase = ecs_singleton!(world, cur MatchClock).phase;
+            let inputs = if !phase.runs(GameSystem::Controls) {
+                for (_entity_id, _pilot, controls) in
+                    ecs_iter!(world, cur Pilot, mut ShipControls)
+                {
+                    *controls = ShipControls::default();
+                }
+                &[]
+            } else {
+                inputs
+            };
             for (entity_id, player_controls) in inputs {
+                // Inputs for a mech that died since the caller last looked
+                // have nowhere to go, and that's fine.
                 if let Some(mut controls) = ecs_get!(world, *entity_id, mut ShipControls) {
                     (*controls).clone_from(player_controls);
-                } else {
-                    eprintln!("WARNING: missing player {entity_id:?}");
                 }
             }
             // Ship Controls System
             for (_entity_id, placement, controls, control_characteristics, physics) in
                 ecs_iter!(world, mut Placement, cur ShipControls, mut ShipControlCharacteristics, mut Physics)
             {
-                physics.apply_force(controls.movement * 0.005);
+                physics.apply_force(controls.movement * control_characteristics.thrust);
                 let facing_angle = placement.angle;
                 let aim_angle = controls.aim.y.atan2(controls.aim.x);
                 let diff = angle_subtract(aim_angle, facing_angle);
-                physics.apply_torque(control_characteristics.aim_controller.get_control_output(diff, physics.angular_velocity) * 0.05);
+                physics.apply_torque(control_characteristics.aim_controller.get_control_output(diff, physics.angular_velocity, dt) * control_characteristics.turn_torque);
+            }
+            let level_geometry = &self.level_geometry;
+            // Weapon System
+            if phase.runs(GameSystem::Combat) {
+                weapons::fire_weapons(world, level_geometry, dt);
             }
             // Physics System
-            let world_physics = ecs_singleton!(world, cur WorldPhysics);
-            for (_entity_id, position, physics) in ecs_iter!(world, mut Placement, mut Physics) {
-                let drag_amount =
-                    physics.velocity.magnitude_squared() * -world_physics.air_thickness;
-                if drag_amount != 0.0 {
-                    physics.apply_force(physics.velocity.normalize() * drag_amount);
+            let substeps = world_physics.substeps.max(1);
+            let step = dt / substeps as f32;
+            for (_entity_id, position, physics, collider) in
+                ecs_iter!(world, mut Placement, mut Physics, cur Option<Collider>)
+            {
+                let radius = collider
+                    .map(|collider| collider.scaled_radius(position))
+                    .unwrap_or(0.0);
+                // Forces from this tick stay the same for every substep, but
+                // drag depends on how fast we're going *right now*.
+                let applied_force = physics.force;
+                for _ in 0..substeps {
+                    let mut force = applied_force;
+                    let drag_amount =
+                        physics.velocity.magnitude_squared() * -world_physics.air_thickness;
+                    if drag_amount != 0.0 {
+                        force += physics.velocity.normalize() * drag_amount;
+                    }
+                    let linear_acceleration = force / physics.mass;
+                    let angular_acceleration = physics.torque / physics.moment;
+                    let old_position = position.position;
+                    position.position +=
+                        (physics.velocity + linear_acceleration * (step * 0.5)) * step;
+                    position.angle +=
+                        (physics.angular_velocity + angular_acceleration * (step * 0.5)) * step;
+                    position.angle %= TAU;
+                    physics.velocity += linear_acceleration * step;
+                    physics.angular_velocity += angular_acceleration * step;
+                    // don't go through walls, slide along them instead
+                    level_geometry.collide_circle(
+                        old_position,
+                        &mut position.position,
+                        radius,
+                        &mut physics.velocity,
+                    );
                 }
-                let linear_acceleration = physics.force / physics.mass;
-                let angular_acceleration = physics.torque / physics.moment;
-                position.position += physics.velocity + linear_acceleration * 0.5;
-                position.angle += physics.angular_velocity + angular_acceleration * 0.5;
-                position.angle %= TAU;
-                physics.velocity += linear_acceleration;
-                physics.angular_velocity += angular_acceleration;
                 // zero out the forces for next tick
                 physics.force = vector![0.0, 0.0];
                 physics.torque = 0.0;
             }
+            // Spatial Hash System
+            let spatial_hash = &mut self.spatial_hash;
+            spatial_hash.rebuild(world);
+            // Projectile System
+            let mut damage = vec![];
+            if phase.runs(GameSystem::Combat) {
+                let friendly_fire = ecs_singleton!(world, cur MatchSettings).friendly_fire;
+                weapons::move_projectiles(
+                    world,
+                    level_geometry,
+                    spatial_hash,
+                    dt,
+                    friendly_fire,
+                    &mut damage,
+                );
+            }
+            // Collision System
+            collision::resolve_collisions(world, level_geometry, spatial_hash);
+            // Damage System
+            let damage = damage::apply_damage(world, &damage);
+            // Goat System
+            goats::update_goats(world, level_geometry, spatial_hash, dt);
+            // Death System
+            if phase.runs(GameSystem::Combat) {
+                damage::handle_deaths(world, &mut events);
+                respawn::queue_respawns(world, &events);
+            }
+            let mode = ecs_singleton!(world, cur MatchSettings).mode.clone();
+            if phase.runs(GameSystem::Ai) {
+                // Boss System
+                boss::update_bosses(
+                    world,
+                    level_geometry,
+                    spatial_hash,
+                    dt,
+                    &damage,
+                    &mut events,
+                );
+                // Unit AI System
+                unit_ai::update_units(world, level_geometry, &self.navigation, spatial_hash);
+                // Game Mode System
+                mode.update(world, dt, &mut events);
+            } else {
+                // Nobody's thinking, so bosses and units stop where they
+                // are. (Goats think for themselves.)
+                for (_entity_id, controls, pilot, goat) in
+                    ecs_iter!(world, mut ShipControls, cur Option<Pilot>, cur Option<Goat>)
+                {
+                    if pilot.is_none() && goat.is_none() {
+                        *controls = ShipControls::default();
+                    }
+                }
+            }
+            // Capture System
+            if phase.runs(GameSystem::Capture) {
+                capture::update_capture_zones(world, spatial_hash, dt, &mut events);
+            }
+            if phase.runs(GameSystem::Economy) {
+                // Income System
+                mode.update_income(world, dt);
+                // Button System
+                purchase::update_buttons(world, spatial_hash, &mut events);
+            }
+            // Respawn System
+            if phase.runs(GameSystem::Respawn) {
+                respawn::update_respawns(world, dt, &mut events);
+            }
+            // Outcome System
+            if phase.runs(GameSystem::Outcome) {
+                outcome::evaluate_outcome(world, dt, &mut events);
+            }
+            transactions = ecs_singleton!(world, mut ResourceBank).take_pending();
         });
+        self.events = events;
+        self.transactions.extend(transactions);
     }
 }
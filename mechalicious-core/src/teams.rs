@@ -0,0 +1,68 @@
+use super::*;
+
+/// True if `entity_id` is on the same team as `team`.
+pub fn is_friendly(world: &EcsWorld, entity_id: EntityId, team: &Team) -> bool {
+    ecs_get!(world, entity_id, cur Team)
+        .map(|other| team.is_friendly_to(other))
+        .unwrap_or(false)
+}
+
+/// True if `entity_id` is on a team that's against `team`.
+pub fn is_hostile(world: &EcsWorld, entity_id: EntityId, team: &Team) -> bool {
+    ecs_get!(world, entity_id, cur Team)
+        .map(|other| team.is_hostile_to(other))
+        .unwrap_or(false)
+}
+
+/// The closest living thing near `position` that's hostile to `team`.
+pub fn nearest_enemy(
+    world: &EcsWorld,
+    spatial_hash: &SpatialHash,
+    position: Point,
+    team: &Team,
+    max_distance: f32,
+) -> Option<EntityId> {
+    spatial_hash
+        .nearest(position, max_distance, |entry| {
+            ecs_get!(world, entry.entity_id, cur Team, cur Health)
+                .map(|(other, health)| team.is_hostile_to(other) && !health.is_dead())
+                .unwrap_or(false)
+        })
+        .map(|entry| entry.entity_id)
+}
+
+/// The closest living thing near `position` that's on the same team as
+/// `team`.
+pub fn nearest_friend(
+    world: &EcsWorld,
+    spatial_hash: &SpatialHash,
+    position: Point,
+    team: &Team,
+    max_distance: f32,
+) -> Option<EntityId> {
+    spatial_hash
+        .nearest(position, max_distance, |entry| {
+            ecs_get!(world, entry.entity_id, cur Team, cur Health)
+                .map(|(other, health)| team.is_friendly_to(other) && !health.is_dead())
+                .unwrap_or(false)
+        })
+        .map(|entry| entry.entity_id)
+}
+
+impl GameWorld {
+    /// See `teams::nearest_enemy`. Positions are as of the last tick.
+    pub fn nearest_enemy(
+        &self,
+        position: Point,
+        team: &Team,
+        max_distance: f32,
+    ) -> Option<EntityId> {
+        nearest_enemy(
+            &self.ecs_world,
+            &self.spatial_hash,
+            position,
+            team,
+            max_distance,
+        )
+    }
+}
@@ -0,0 +1,292 @@
+use super::*;
+
+// How far away a unit notices things it could shoot.
+const UNIT_SIGHT: f32 = 6.0;
+// How far where a unit's going can move before it bothers finding a new
+// way there.
+const REPLAN_DISTANCE: f32 = 1.0;
+// How close a unit has to get to a waypoint for it to count.
+const WAYPOINT_REACHED: f32 = 0.5;
+
+// What one unit knows about itself, before it thinks.
+struct UnitSnapshot {
+    entity_id: EntityId,
+    position: Point,
+    velocity: Vector,
+    radius: f32,
+    team: Team,
+    objective: Option<EntityId>,
+    weapon_range: f32,
+    behavior: UnitBehavior,
+    target_priority: TargetPriority,
+    path: Vec<Point>,
+    path_destination: Option<Point>,
+    path_revision: u64,
+}
+
+/// Thinks for every attack unit: which node it's going after, who it's
+/// fighting on the way, and where it's going to move and shoot. What it
+/// decides goes in its `ShipControls`, same as a player's input would.
+pub(crate) fn update_units(
+    world: &EcsWorld,
+    level_geometry: &LevelGeometry,
+    navigation: &Navigation,
+    spatial_hash: &SpatialHash,
+) {
+    let units: Vec<UnitSnapshot> = ecs_iter!(
+        world,
+        cur Unit,
+        cur Placement,
+        cur Physics,
+        cur Collider,
+        cur Team,
+        cur Weapon
+    )
+    .map(
+        |(entity_id, unit, placement, physics, collider, team, weapon)| UnitSnapshot {
+            entity_id,
+            position: placement.position,
+            velocity: physics.velocity,
+            radius: collider.scaled_radius(placement),
+            team: *team,
+            objective: unit.objective,
+            weapon_range: weapon.projectile_speed * weapon.projectile_lifetime,
+            behavior: unit.behavior,
+            target_priority: unit.target_priority,
+            path: unit.path.clone(),
+            path_destination: unit.path_destination,
+            path_revision: unit.path_revision,
+        },
+    )
+    .collect();
+    if units.is_empty() {
+        return;
+    }
+    // Everything units can capture: the nodes, and in PVE, the core.
+    // (entity ID, position, owner, guardian)
+    let nodes: Vec<(EntityId, Point, Team, Option<EntityId>)> =
+        ecs_iter!(world, cur Placement, cur Team, cur CaptureZone)
+            .filter(|(_entity_id, _placement, _team, zone)| zone.captured_by == CapturerKind::Unit)
+            .map(|(entity_id, placement, team, zone)| {
+                (entity_id, placement.position, *team, zone.guardian)
+            })
+            .collect();
+    for unit in units {
+        let closest_node = |wanted: &dyn Fn(&Team) -> bool| {
+            nodes.iter().filter(|node| wanted(&node.2)).min_by(|a, b| {
+                (a.1 - unit.position)
+                    .magnitude_squared()
+                    .total_cmp(&(b.1 - unit.position).magnitude_squared())
+            })
+        };
+        let not_ours = |team: &Team| *team != unit.team;
+        let objective = match unit.behavior {
+            // Keep going after the same node until it's ours. Then find
+            // another.
+            UnitBehavior::Assault | UnitBehavior::Hunter => unit
+                .objective
+                .and_then(|objective| nodes.iter().find(|node| node.0 == objective))
+                .filter(|node| not_ours(&node.2))
+                .or_else(|| closest_node(&not_ours)),
+            // Stick close to home. If there's no home, go make one.
+            UnitBehavior::Defender => {
+                closest_node(&|team: &Team| *team == unit.team).or_else(|| closest_node(&not_ours))
+            }
+        };
+        // Shoot at enemies that are close enough to see, whichever we like
+        // best. If there aren't any, shoot at whatever's guarding the node.
+        let target =
+            pick_target(world, level_geometry, spatial_hash, &unit, UNIT_SIGHT).or_else(|| {
+                let guardian = objective?.3?;
+                if !is_alive(world, guardian) {
+                    return None;
+                }
+                let position = ecs_get!(world, guardian, cur Placement)?.position;
+                ((position - unit.position).magnitude() <= UNIT_SIGHT
+                    && level_geometry.line_of_sight(unit.position, position))
+                .then_some((guardian, position))
+            });
+        // Hunters don't wait for things to come into view.
+        let quarry = match (unit.behavior, target) {
+            (UnitBehavior::Hunter, None) => spatial_hash
+                .nearest(unit.position, f32::INFINITY, |entry| {
+                    is_enemy(world, &unit, entry.entity_id)
+                })
+                .map(|entry| entry.position),
+            _ => None,
+        };
+        // Where to go: into the fight, or onto the node.
+        let destination = match (target, objective) {
+            _ if quarry.is_some() => quarry,
+            (Some((_, target_position)), _) => {
+                let offset = unit.position - target_position;
+                // get in range, then stay there
+                let range = unit.weapon_range * 0.7;
+                if offset.magnitude() > range {
+                    Some(target_position + offset.normalize() * range)
+                } else {
+                    Some(unit.position)
+                }
+            }
+            (None, Some(node)) => Some(node.1),
+            (None, None) => None,
+        };
+        let mut path = unit.path.clone();
+        let replan = match (destination, unit.path_destination, path.first()) {
+            (Some(destination), Some(path_destination), Some(waypoint)) => {
+                (destination - path_destination).magnitude() > REPLAN_DISTANCE
+                    || (waypoint - unit.position).magnitude() <= WAYPOINT_REACHED
+                    || unit.path_revision != navigation.revision()
+            }
+            (Some(_), _, _) => true,
+            (None, _, _) => false,
+        };
+        if replan {
+            if let Some(destination) = destination {
+                // if there's no way there, go as straight there as the walls
+                // will let us
+                path = navigation
+                    .find_path(level_geometry, unit.position, destination, unit.radius)
+                    .filter(|path| !path.is_empty())
+                    .unwrap_or_else(|| vec![destination]);
+            }
+        }
+        if destination.is_none() {
+            path.clear();
+        }
+        let movement = match path.first() {
+            Some(waypoint) => steer_toward(unit.position, unit.velocity, *waypoint),
+            None => steer_toward(unit.position, unit.velocity, unit.position),
+        };
+        let Some((mut unit_state, mut controls)) =
+            ecs_get!(world, unit.entity_id, mut Unit, mut ShipControls)
+        else {
+            continue;
+        };
+        unit_state.objective = objective.map(|node| node.0);
+        unit_state.target = target.map(|(target, _)| target);
+        if replan {
+            unit_state.path_destination = destination;
+            unit_state.path_revision = navigation.revision();
+        }
+        unit_state.path = path;
+        controls.movement = movement;
+        match target {
+            Some((_, target_position)) => {
+                controls.aim = target_position - unit.position;
+                controls.fire = true;
+            }
+            None => {
+                if movement.magnitude_squared() > 0.0 {
+                    controls.aim = movement;
+                }
+                controls.fire = false;
+            }
+        }
+    }
+}
+
+// Something that a unit should shoot at if it gets the chance.
+fn is_enemy(world: &EcsWorld, unit: &UnitSnapshot, entity_id: EntityId) -> bool {
+    entity_id != unit.entity_id
+        && teams::is_hostile(world, entity_id, &unit.team)
+        && is_alive(world, entity_id)
+}
+
+// Out of all the enemies this unit can see, the one it likes best, and where
+// it is.
+fn pick_target(
+    world: &EcsWorld,
+    level_geometry: &LevelGeometry,
+    spatial_hash: &SpatialHash,
+    unit: &UnitSnapshot,
+    sight: f32,
+) -> Option<(EntityId, Point)> {
+    let mut best: Option<((f32, f32, EntityId), Point)> = None;
+    for entry in spatial_hash.query_radius(unit.position, sight) {
+        if !is_enemy(world, unit, entry.entity_id)
+            || !level_geometry.line_of_sight(unit.position, entry.position)
+        {
+            continue;
+        }
+        let distance = (entry.position - unit.position).magnitude();
+        // lowest first: what we care about most, then how close it is, then
+        // the entity ID so ties always go the same way
+        let preference = match unit.target_priority {
+            TargetPriority::Nearest => 0.0,
+            TargetPriority::Weakest => ecs_get!(world, entry.entity_id, cur Health)
+                .map(|health| health.current)
+                .unwrap_or(f32::INFINITY),
+            TargetPriority::Mechs => {
+                if ecs_get!(world, entry.entity_id, cur Pilot).is_some() {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            TargetPriority::Units => {
+                if ecs_get!(world, entry.entity_id, cur Unit).is_some() {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        };
+        let key = (preference, distance, entry.entity_id);
+        let better = match &best {
+            None => true,
+            Some((best_key, _)) => key
+                .0
+                .total_cmp(&best_key.0)
+                .then(key.1.total_cmp(&best_key.1))
+                .then(key.2.cmp(&best_key.2))
+                .is_lt(),
+        };
+        if better {
+            best = Some((key, entry.position));
+        }
+    }
+    best.map(|((_, _, entity_id), position)| (entity_id, position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_state(world: &GameWorld, unit: EntityId) -> Unit {
+        let world = world.get_ecs_world();
+        ecs_get!(world, unit, cur Unit).unwrap().clone()
+    }
+
+    #[test]
+    fn units_stick_to_their_paths_until_the_level_changes() {
+        let mut world = GameWorld::load_level(
+            "spawn 0 0 0\nbutton grunt 3 0 -3 0\nwall 2 -3 2 3\nnode 1 6 0\nnode 1 -12 0 0",
+        )
+        .unwrap();
+        let player = world.add_player(TeamId(0));
+        world.tick(&[]);
+        // stand on the button
+        let mech = world.get_player_mech(player).unwrap();
+        world.with_ecs_world(|world| {
+            ecs_get!(world, mech, mut Placement).unwrap().position = point![3.0, 0.0];
+        });
+        world.tick(&[]);
+        world.tick(&[]);
+        let world_ref = world.get_ecs_world();
+        let (unit, _) = ecs_iter!(world_ref, cur Unit).next().unwrap();
+        // The wall's in the way, so it has to go around.
+        let planned = unit_state(&world, unit);
+        assert!(planned.path.len() > 1, "{:?}", planned.path);
+        assert_eq!(planned.path_destination, Some(point![6.0, 0.0]));
+        world.tick(&[]);
+        assert_eq!(unit_state(&world, unit).path, planned.path);
+        // Take the wall away, and it goes straight there.
+        world.set_level_geometry(LevelGeometry::default());
+        world.tick(&[]);
+        let replanned = unit_state(&world, unit);
+        assert_eq!(replanned.path, vec![point![6.0, 0.0]]);
+        assert_eq!(replanned.path_revision, world.navigation.revision());
+        assert_ne!(replanned.path_revision, planned.path_revision);
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::BTreeMap;
+
+use super::*;
+
+/*
+
+Unit files are plain text, like level files: one thing per line, and
+anything after a `#` is a comment. Each `unit` line starts a new kind of
+unit, and everything after it (up to the next `unit` line) is about that
+one.
+
+    unit NAME
+    cost AMOUNT                        what it takes out of the bank
+    health AMOUNT
+    thrust AMOUNT                      how hard it pushes (so, how fast
+                                       it goes)
+    size RADIUS
+    weapon FIRE_RATE DAMAGE PROJECTILE_SPEED LIFETIME [SPREAD]
+    model PATH
+    behavior assault|hunter|defender   (optional, assault if not given)
+    priority nearest|weakest|mechs|units
+                                       (optional, nearest if not given)
+
+Everything but `behavior` and `priority` has to be there.
+
+*/
+
+/// What a unit spends its time doing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitBehavior {
+    /// Go take the nearest node that isn't ours, fighting whatever's in the
+    /// way.
+    #[default]
+    Assault,
+    /// Go after the nearest enemy, wherever it is.
+    Hunter,
+    /// Hang around the nearest node that is ours, and shoot at anybody who
+    /// comes for it.
+    Defender,
+}
+
+/// Who a unit would rather shoot, out of everybody it can see.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetPriority {
+    #[default]
+    Nearest,
+    /// Whoever has the least health left.
+    Weakest,
+    /// Players first.
+    Mechs,
+    /// Other units first.
+    Units,
+}
+
+/// Everything that makes one kind of attack unit different from another.
+#[derive(Clone, Debug)]
+pub struct UnitType {
+    pub name: String,
+    pub cost: f32,
+    pub max_health: f32,
+    pub thrust: f32,
+    /// How big it is. Its `Collider` is this big, and so is its model.
+    pub size: f32,
+    /// What it shoots with. `cooldown` is where it starts out.
+    pub weapon: Weapon,
+    pub model_path: &'static str,
+    pub behavior: UnitBehavior,
+    pub target_priority: TargetPriority,
+}
+
+/// All the kinds of units that can be bought, by name.
+#[derive(Clone, Debug)]
+pub struct UnitTypes {
+    types: BTreeMap<String, UnitType>,
+}
+
+impl Default for UnitTypes {
+    fn default() -> Self {
+        UnitTypes::parse(include_str!("../data/default.units"))
+            .unwrap_or_else(|err| panic!("Unable to parse default.units: {err}"))
+    }
+}
+
+// A unit from a unit file, as we're reading it.
+struct PartialUnitType {
+    name: String,
+    line_number: usize,
+    cost: Option<f32>,
+    max_health: Option<f32>,
+    thrust: Option<f32>,
+    size: Option<f32>,
+    weapon: Option<Weapon>,
+    model_path: Option<&'static str>,
+    behavior: UnitBehavior,
+    target_priority: TargetPriority,
+}
+
+impl PartialUnitType {
+    fn finish(self) -> Result<UnitType, LevelParseError> {
+        let missing = |what: &str| LevelParseError {
+            line_number: self.line_number,
+            message: format!("unit `{}` is missing {what}", self.name),
+        };
+        Ok(UnitType {
+            cost: self.cost.ok_or_else(|| missing("a cost"))?,
+            max_health: self.max_health.ok_or_else(|| missing("a health"))?,
+            thrust: self.thrust.ok_or_else(|| missing("a thrust"))?,
+            size: self.size.ok_or_else(|| missing("a size"))?,
+            weapon: self.weapon.clone().ok_or_else(|| missing("a weapon"))?,
+            model_path: self.model_path.ok_or_else(|| missing("a model"))?,
+            behavior: self.behavior,
+            target_priority: self.target_priority,
+            name: self.name,
+        })
+    }
+}
+
+impl UnitTypes {
+    /// Reads a unit file. Its errors look just like level file errors.
+    pub fn parse(source: &str) -> Result<UnitTypes, LevelParseError> {
+        let mut ret = UnitTypes {
+            types: BTreeMap::new(),
+        };
+        let mut current: Option<PartialUnitType> = None;
+        for mut line in LevelLine::split(source) {
+            if line.command == "unit" {
+                if let Some(unit_type) = current.take() {
+                    ret.insert(unit_type.finish()?);
+                }
+                current = Some(PartialUnitType {
+                    name: line.next_word("a name")?.to_string(),
+                    line_number: line.line_number,
+                    cost: None,
+                    max_health: None,
+                    thrust: None,
+                    size: None,
+                    weapon: None,
+                    model_path: None,
+                    behavior: UnitBehavior::default(),
+                    target_priority: TargetPriority::default(),
+                });
+                line.finish()?;
+                continue;
+            }
+            let Some(unit_type) = current.as_mut() else {
+                return Err(line.error("expected a `unit` line first"));
+            };
+            match line.command {
+                "cost" => unit_type.cost = Some(line.next_non_negative("a cost")?),
+                "health" => unit_type.max_health = Some(line.next_positive("a health")?),
+                "thrust" => unit_type.thrust = Some(line.next_positive("a thrust")?),
+                "size" => unit_type.size = Some(line.next_positive("a size")?),
+                "weapon" => {
+                    let fire_rate = line.next_positive("a fire rate")?;
+                    let damage = line.next_positive("a damage")?;
+                    let projectile_speed = line.next_positive("a projectile speed")?;
+                    let projectile_lifetime = line.next_positive("a lifetime")?;
+                    let spread = line.optional_number("a spread")?.unwrap_or(0.0);
+                    unit_type.weapon = Some(Weapon {
+                        fire_rate,
+                        muzzle_offset: vector![1.1, 0.0],
+                        projectile_speed,
+                        spread,
+                        projectile_lifetime,
+                        damage,
+                        cooldown: 0.0,
+                    });
+                }
+                "model" => {
+                    unit_type.model_path = Some(intern_model_path(line.next_word("a model")?))
+                }
+                "behavior" => {
+                    unit_type.behavior = match line.next_word("a behavior")? {
+                        "assault" => UnitBehavior::Assault,
+                        "hunter" => UnitBehavior::Hunter,
+                        "defender" => UnitBehavior::Defender,
+                        other => return Err(line.error(format!("unknown behavior `{other}`"))),
+                    }
+                }
+                "priority" => {
+                    unit_type.target_priority = match line.next_word("a priority")? {
+                        "nearest" => TargetPriority::Nearest,
+                        "weakest" => TargetPriority::Weakest,
+                        "mechs" => TargetPriority::Mechs,
+                        "units" => TargetPriority::Units,
+                        other => return Err(line.error(format!("unknown priority `{other}`"))),
+                    }
+                }
+                command => return Err(line.error(format!("unknown command `{command}`"))),
+            }
+            line.finish()?;
+        }
+        if let Some(unit_type) = current {
+            ret.insert(unit_type.finish()?);
+        }
+        Ok(ret)
+    }
+    pub fn get(&self, name: &str) -> Option<&UnitType> {
+        self.types.get(name)
+    }
+    /// Adds a new kind of unit, or replaces the one with the same name.
+    pub fn insert(&mut self, unit_type: UnitType) {
+        self.types.insert(unit_type.name.clone(), unit_type);
+    }
+    /// Adds (or replaces) every kind of unit in `other`.
+    pub fn extend(&mut self, other: UnitTypes) {
+        self.types.extend(other.types);
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &UnitType> {
+        self.types.values()
+    }
+}
+
+/// One of the little guys that a team buys to go take nodes.
+#[derive(Clone, Debug)]
+pub struct Unit {
+    pub unit_type: String,
+    pub behavior: UnitBehavior,
+    pub target_priority: TargetPriority,
+    /// The node it's going to take.
+    pub objective: Option<EntityId>,
+    /// Whoever it's shooting at right now.
+    pub target: Option<EntityId>,
+    /// How it's getting where it's going, next waypoint first. It sticks to
+    /// this until it gets to the waypoint, decides to go somewhere else, or
+    /// the level changes.
+    pub path: Vec<Point>,
+    /// Where `path` goes, and which `Navigation::revision` it was found on.
+    pub path_destination: Option<Point>,
+    pub path_revision: u64,
+}
+
+pub(crate) fn spawn_unit(
+    world: &mut EcsWorld,
+    unit_type: &UnitType,
+    position: Point,
+    team: Team,
+) -> EntityId {
+    ecs_spawn!(
+        world,
+        Placement {
+            position,
+            angle: 0.0,
+            scale: unit_type.size,
+        },
+        Physics {
+            mass: 0.5,
+            moment: 0.5,
+            force: vector![0.0, 0.0],
+            torque: 0.0,
+            velocity: vector![0.0, 0.0],
+            angular_velocity: 0.0,
+        },
+        Collider {
+            radius: 1.0,
+            restitution: 0.3,
+            friction: 0.2,
+        },
+        ShipControls::default(),
+        ShipControlCharacteristics {
+            thrust: unit_type.thrust,
+            turn_torque: 90.0,
+            ..Default::default()
+        },
+        unit_type.weapon.clone(),
+        Health::new(unit_type.max_health, DeathBehavior::Despawn),
+        team,
+        Capturer {
+            kind: CapturerKind::Unit,
+        },
+        Unit {
+            unit_type: unit_type.name.clone(),
+            behavior: unit_type.behavior,
+            target_priority: unit_type.target_priority,
+            objective: None,
+            target: None,
+            path: vec![],
+            path_destination: None,
+            path_revision: 0,
+        },
+        Visible {
+            model_path: unit_type.model_path,
+        },
+    )
+}
+
+impl GameWorld {
+    /// Reads a unit file, and makes all the kinds of units in it available
+    /// to buy. Any that have the same name as one we already had replace
+    /// it.
+    pub fn load_unit_types(&mut self, source: &str) -> Result<(), LevelParseError> {
+        let new_types = UnitTypes::parse(source)?;
+        self.ecs_world = self.ecs_world.buffered_tick(|world| {
+            ecs_singleton!(world, mut UnitTypes).extend(new_types);
+        });
+        Ok(())
+    }
+    /// How many units `team_id` has out on the field.
+    pub fn count_units(&self, team_id: TeamId) -> usize {
+        let world: &EcsWorld = &self.ecs_world;
+        count_units(world, team_id)
+    }
+}
+
+pub(crate) fn count_units(world: &EcsWorld, team_id: TeamId) -> usize {
+    ecs_iter!(world, cur Unit, cur Team)
+        .filter(|(_entity_id, _unit, team)| team.0 == Some(team_id))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRUNT: &str = "unit grunt
+cost 10
+health 5
+thrust 20
+size 0.1
+weapon 1 1 1 1
+model grunt.v2d
+";
+
+    #[test]
+    fn parses_a_unit() {
+        let unit_types = UnitTypes::parse(GRUNT).unwrap();
+        let grunt = unit_types.get("grunt").unwrap();
+        assert_eq!(grunt.cost, 10.0);
+        assert_eq!(grunt.weapon.damage, 1.0);
+    }
+
+    #[test]
+    fn rejects_negative_costs_and_damage() {
+        let err = UnitTypes::parse(&GRUNT.replace("cost 10", "cost -5")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: expected a cost to be zero or more, not `-5`"
+        );
+        let err =
+            UnitTypes::parse(&GRUNT.replace("weapon 1 1 1 1", "weapon 1 -1 1 1")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 6: expected a damage to be more than zero, not `-1`"
+        );
+        assert!(UnitTypes::parse(&GRUNT.replace("cost 10", "cost 0")).is_ok());
+    }
+}
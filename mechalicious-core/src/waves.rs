@@ -0,0 +1,233 @@
+use super::*;
+
+/// How PVE waves come in, and what they're worth.
+#[derive(Clone, Debug)]
+pub struct WaveSettings {
+    /// How long the defenders get to settle in before the first wave.
+    pub first_wave_delay: f32, // seconds
+    pub wave_interval: f32, // seconds
+    /// How many units are in the first wave.
+    pub base_size: usize,
+    /// How many more units each wave has than the one before it.
+    pub size_growth: f32,
+    /// No wave is bigger than this.
+    pub max_size: usize,
+    /// Waves stop spawning units while the attackers have this many.
+    pub max_alive: usize,
+    /// A new (more expensive) kind of unit joins the waves this often.
+    pub waves_per_new_unit_type: u32,
+    /// What the defenders get at the start of every wave...
+    pub base_bonus: f32,
+    /// ...plus this much for every tier of node they own.
+    pub bonus_per_tier: f32,
+}
+
+impl Default for WaveSettings {
+    fn default() -> Self {
+        Self {
+            first_wave_delay: 10.0,
+            wave_interval: 30.0,
+            base_size: 3,
+            size_growth: 1.0,
+            max_size: 40,
+            max_alive: 60,
+            waves_per_new_unit_type: 3,
+            base_bonus: 200.0,
+            bonus_per_tier: 50.0,
+        }
+    }
+}
+
+impl WaveSettings {
+    /// How many units are in wave `wave` (counting from 1).
+    pub fn wave_size(&self, wave: u32) -> usize {
+        let growth = (self.size_growth * wave.saturating_sub(1) as f32).max(0.0) as usize;
+        self.base_size.saturating_add(growth).min(self.max_size)
+    }
+}
+
+/// Keeps track of the waves in a PVE match.
+#[derive(Clone, Debug, Default)]
+pub struct WaveDirector {
+    /// Whether the core's been handed over to the defenders yet.
+    pub started: bool,
+    /// The last wave that came in. 0 before the first one.
+    pub wave: u32,
+    /// Until the next wave.
+    pub time_left: f32, // seconds
+}
+
+/// Runs PVE mode: hands the core (and its guardian) to the defenders, then
+/// sends in a wave of the attackers' units every so often, and pays the
+/// defenders for every wave they see.
+pub(crate) fn update_waves(world: &mut EcsWorld, mode: &PvE, dt: f32, events: &mut Vec<GameEvent>) {
+    let (defenders, attackers) = (mode.defenders, mode.attackers);
+    let wave_settings = &mode.waves;
+    let wave = {
+        let director = ecs_singleton!(world, mut WaveDirector);
+        if !director.started {
+            director.started = true;
+            director.time_left = wave_settings.first_wave_delay;
+            start_defending(world, defenders);
+        }
+        let director = ecs_singleton!(world, mut WaveDirector);
+        director.time_left -= dt;
+        if director.time_left > 0.0 {
+            return;
+        }
+        director.time_left += wave_settings.wave_interval.max(dt);
+        director.wave += 1;
+        director.wave
+    };
+    // Pay the defenders.
+    let owned_tiers: u32 = ecs_iter!(world, cur ResourceNode, cur Team)
+        .filter(|(_entity_id, _node, team)| **team == Team::of(defenders))
+        .map(|(_entity_id, node, _team)| node.tier)
+        .sum();
+    let bonus = wave_settings.base_bonus + wave_settings.bonus_per_tier * owned_tiers as f32;
+    ecs_singleton!(world, mut ResourceBank).credit(defenders, bonus, TransactionReason::WaveBonus);
+    // Send in the attackers.
+    let composition = wave_composition(world, wave_settings, wave);
+    let entrances = entrances(world, defenders);
+    let room = wave_settings
+        .max_alive
+        .saturating_sub(count_units(world, attackers));
+    let mut spawned = 0;
+    if !entrances.is_empty() {
+        for (n, unit_type) in composition.iter().take(room).enumerate() {
+            // Spread out around the entrances so they don't all start on top
+            // of each other.
+            let entrance = entrances[n % entrances.len()];
+            let ring = n / entrances.len();
+            let angle = ring as f32 * 2.4;
+            let offset = vector![angle.cos(), angle.sin()] * (ring as f32).sqrt().min(2.0);
+            spawn_unit(world, unit_type, entrance + offset, Team::of(attackers));
+            spawned += 1;
+        }
+    }
+    events.push(GameEvent::WaveStarted {
+        wave,
+        units: spawned,
+        bonus,
+    });
+}
+
+// The core becomes the defenders' (and so does its guardian), and from now
+// on, it's the attackers' units that get to take it away.
+fn start_defending(world: &EcsWorld, defenders: TeamId) {
+    for (_entity_id, _core, team, zone) in ecs_iter!(world, cur Core, mut Team, mut CaptureZone) {
+        *team = Team::of(defenders);
+        zone.captured_by = CapturerKind::Unit;
+        zone.capturing_team = None;
+        zone.progress = 0.0;
+        if let Some(guardian) = zone.guardian {
+            if let Some(mut guardian_team) = ecs_get!(world, guardian, mut Team) {
+                *guardian_team = Team::of(defenders);
+            }
+        }
+    }
+}
+
+// What's in wave `wave`. Early waves are all the cheapest kind of unit. Every
+// so often, the next most expensive kind joins in, and the newest kinds get
+// the most slots.
+fn wave_composition(world: &EcsWorld, wave_settings: &WaveSettings, wave: u32) -> Vec<UnitType> {
+    let mut unit_types: Vec<&UnitType> = ecs_singleton!(world, cur UnitTypes).iter().collect();
+    if unit_types.is_empty() {
+        return vec![];
+    }
+    unit_types.sort_by(|a, b| a.cost.total_cmp(&b.cost).then(a.name.cmp(&b.name)));
+    let unlocked = (1 + (wave - 1) / wave_settings.waves_per_new_unit_type.max(1)) as usize;
+    let unlocked = unlocked.min(unit_types.len());
+    (0..wave_settings.wave_size(wave))
+        .map(|n| unit_types[unlocked - 1 - n % unlocked].clone())
+        .collect()
+}
+
+// Where waves come in: every spawn point the defenders don't own, or if they
+// own them all, every spawn point.
+fn entrances(world: &EcsWorld, defenders: TeamId) -> Vec<Point> {
+    let spawn_points: Vec<(Point, Team)> =
+        ecs_iter!(world, cur SpawnPoint, cur Placement, cur Team)
+            .map(|(_entity_id, _spawn_point, placement, team)| (placement.position, *team))
+            .collect();
+    let not_theirs: Vec<Point> = spawn_points
+        .iter()
+        .filter(|(_, team)| *team != Team::of(defenders))
+        .map(|(position, _)| *position)
+        .collect();
+    if not_theirs.is_empty() {
+        spawn_points.iter().map(|(position, _)| *position).collect()
+    } else {
+        not_theirs
+    }
+}
+
+impl GameWorld {
+    /// Which PVE wave we're on (0 before the first one), and how long until
+    /// the next one.
+    pub fn get_wave(&self) -> (u32, f32) {
+        let world: &EcsWorld = &self.ecs_world;
+        let director = ecs_singleton!(world, cur WaveDirector);
+        (director.wave, director.time_left.max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn thousands_of_waves() {
+        let mut world = GameWorld::load_level_seeded(
+            include_str!("../../mechalicious-client/data/test.level"),
+            42,
+        )
+        .unwrap();
+        let (defenders, attackers) = (TeamId(0), TeamId(1));
+        let waves = WaveSettings {
+            first_wave_delay: 0.0,
+            wave_interval: 0.0,
+            max_alive: 30,
+            ..Default::default()
+        };
+        world.set_match_settings(MatchSettings {
+            mode: Arc::new(PvE {
+                waves: waves.clone(),
+                ..PvE::new(defenders, attackers)
+            }),
+            ..Default::default()
+        });
+        world.add_player(defenders);
+        let mut last_wave = 0;
+        let mut bonuses = 0;
+        for _ in 0..1200 {
+            world.tick(&[]);
+            assert!(count_units(&world.ecs_world, attackers) <= waves.max_alive);
+            for event in world.get_events() {
+                if let GameEvent::WaveStarted { wave, .. } = event {
+                    assert_eq!(*wave, last_wave + 1);
+                    last_wave = *wave;
+                }
+            }
+            let tick = ecs_singleton!(&world.ecs_world, cur ResourceBank).current_tick();
+            bonuses += world
+                .get_transactions(tick)
+                .iter()
+                .filter(|transaction| {
+                    transaction.reason == TransactionReason::WaveBonus
+                        && transaction.team_id == defenders
+                        && transaction.amount >= waves.base_bonus
+                })
+                .count();
+            if world.get_outcome().is_some() {
+                break;
+            }
+        }
+        assert!(last_wave >= 1000, "only got to wave {last_wave}");
+        assert_eq!(world.get_wave().0, last_wave);
+        assert_eq!(bonuses, last_wave as usize);
+    }
+}
@@ -0,0 +1,188 @@
+use super::*;
+
+// Nothing gets more shots than this out in one tick, however fast it fires.
+const MAX_SHOTS_PER_TICK: usize = 16;
+
+/// Makes everything that's holding the trigger (and has cooled down) shoot.
+/// A gun with its muzzle through a wall still goes off, but the bullet ends
+/// up in the wall.
+pub(crate) fn fire_weapons(world: &mut EcsWorld, level_geometry: &LevelGeometry, dt: f32) {
+    let rng = ecs_singleton!(world, mut GameRng);
+    let mut new_projectiles = vec![];
+    for (entity_id, placement, controls, weapon, physics, team) in ecs_iter!(
+        world,
+        cur Placement,
+        cur ShipControls,
+        mut Weapon,
+        cur Option<Physics>,
+        cur Option<Team>
+    ) {
+        // A weapon that can't fire (or can fire infinitely fast) would have
+        // us here forever.
+        if !(weapon.fire_rate.is_finite() && weapon.fire_rate > 0.0) {
+            continue;
+        }
+        weapon.cooldown -= dt;
+        if !controls.fire {
+            weapon.cooldown = weapon.cooldown.max(0.0);
+            continue;
+        }
+        let shooter_velocity = physics.map(|x| x.velocity).unwrap_or_default();
+        let muzzle = placement.as_similarity() * Point::from(weapon.muzzle_offset);
+        let muzzle_clear = level_geometry.line_of_sight(placement.position, muzzle);
+        // If the fire rate is faster than the tick rate, we might get more
+        // than one shot out this tick.
+        let mut shots = 0;
+        while weapon.cooldown <= 0.0 {
+            if shots == MAX_SHOTS_PER_TICK {
+                // can't keep up, so don't try to catch up later either
+                weapon.cooldown = 0.0;
+                break;
+            }
+            shots += 1;
+            let half_spread = weapon.spread * 0.5;
+            let angle = if half_spread > 0.0 && half_spread.is_finite() {
+                placement.angle + rng.gen_range(-half_spread..=half_spread)
+            } else {
+                placement.angle
+            };
+            weapon.cooldown += 1.0 / weapon.fire_rate;
+            if !muzzle_clear {
+                continue;
+            }
+            let direction = vector![angle.cos(), angle.sin()];
+            new_projectiles.push((
+                Placement {
+                    position: muzzle,
+                    angle,
+                    scale: 0.05,
+                },
+                Projectile {
+                    shooter: entity_id,
+                    velocity: shooter_velocity + direction * weapon.projectile_speed,
+                    damage: weapon.damage,
+                    time_left: weapon.projectile_lifetime,
+                },
+                team.copied().unwrap_or_default(),
+            ));
+        }
+    }
+    for (placement, projectile, team) in new_projectiles {
+        ecs_spawn!(
+            world,
+            placement,
+            projectile,
+            team,
+            Visible {
+                model_path: "bullet.v2d",
+            },
+        );
+    }
+}
+
+/// Moves every projectile along. Projectiles that run out of time, or hit
+/// something, go away. Hitting something with `Health` hurts it, unless it's
+/// on the same team and the match says that doesn't count.
+pub(crate) fn move_projectiles(
+    world: &mut EcsWorld,
+    level_geometry: &LevelGeometry,
+    spatial_hash: &SpatialHash,
+    dt: f32,
+    friendly_fire: FriendlyFire,
+    damage: &mut Vec<Damage>,
+) {
+    let mut dead_projectiles = vec![];
+    for (entity_id, placement, projectile, team) in
+        ecs_iter!(world, mut Placement, mut Projectile, cur Team)
+    {
+        projectile.time_left -= dt;
+        if projectile.time_left <= 0.0 {
+            dead_projectiles.push(entity_id);
+            continue;
+        }
+        let travel = projectile.velocity * dt;
+        let hit = raycast::raycast(
+            level_geometry,
+            spatial_hash,
+            placement.position,
+            travel,
+            travel.magnitude(),
+            |target| {
+                target != projectile.shooter
+                    && (friendly_fire != FriendlyFire::PassThrough
+                        || !teams::is_friendly(world, target, team))
+            },
+        );
+        match hit {
+            None => placement.position += travel,
+            Some(hit) => {
+                if let RaycastTarget::Entity(target) = hit.target {
+                    if friendly_fire == FriendlyFire::Blocked
+                        && teams::is_friendly(world, target, team)
+                    {
+                        dead_projectiles.push(entity_id);
+                        continue;
+                    }
+                    damage.push(Damage {
+                        target,
+                        amount: projectile.damage,
+                        attacker: Some(projectile.shooter),
+                    });
+                }
+                dead_projectiles.push(entity_id);
+            }
+        }
+    }
+    for entity_id in dead_projectiles {
+        world.despawn(entity_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_shooting_through_walls() {
+        let mut world = GameWorld::load_level("wall 1 -5 1 5\nspawn 0 0 0").unwrap();
+        let player = world.add_player(TeamId(0));
+        world.tick(&[]);
+        let mech = world.get_player_mech(player).unwrap();
+        // right up against the wall, with the gun poking through it
+        world.with_ecs_world(|world| {
+            let mut placement = ecs_get!(world, mech, mut Placement).unwrap();
+            placement.position = point![0.7, 0.0];
+            placement.angle = 0.0;
+        });
+        let controls = ShipControls {
+            aim: vector![1.0, 0.0],
+            fire: true,
+            ..Default::default()
+        };
+        for _ in 0..30 {
+            world.tick(&[(mech, &controls)]);
+            let world = world.get_ecs_world();
+            for (_entity_id, _projectile, placement) in
+                ecs_iter!(world, cur Projectile, cur Placement)
+            {
+                assert!(placement.position.x < 1.0, "{:?}", placement.position);
+            }
+        }
+        // and it still works away from the wall
+        world.with_ecs_world(|world| {
+            let mut placement = ecs_get!(world, mech, mut Placement).unwrap();
+            placement.position = point![-2.0, 0.0];
+            placement.angle = PI;
+        });
+        let controls = ShipControls {
+            aim: vector![-1.0, 0.0],
+            fire: true,
+            ..Default::default()
+        };
+        for _ in 0..30 {
+            world.tick(&[(mech, &controls)]);
+        }
+        let world = world.get_ecs_world();
+        assert!(ecs_iter!(world, cur Projectile).count() > 0);
+    }
+}